@@ -30,6 +30,7 @@ async fn main() {
                 user: USERNAME.to_string(),
                 password: PASSWORD.to_string(),
                 system_name: SYSTEM_NAME.to_string(),
+                ..RithmicConnectionInfo::default()
             };
 
             let ticker_plant = RithmicTickerPlant::new(&rcinf).await;
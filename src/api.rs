@@ -1,28 +1,313 @@
+use std::{collections::HashMap, sync::Arc};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use bytes::{BufMut, Bytes, BytesMut};
 use serde::{Deserialize, Serialize};
 
+use receiver_api::{RithmicReceiverApi, RithmicResponse};
+use templates::*;
+
+
+pub mod frame_capture;
 pub mod receiver_api;
 pub mod rithmic_command_types;
 pub mod sender_api;
+pub mod templates;
 
 
 pub static DEFAULT_RTI_WS_URL: &str = "wss://rprotocol-mobile.rithmic.com";
 
+/// Protocol version the messages in `rti` were generated from
+pub static TEMPLATE_VERSION: &str = "5.27";
+
+/// Template ids that `RithmicSenderApi` can encode, in ascending order
+pub fn supported_request_templates() -> &'static [u16] {
+    REQUEST_TEMPLATES
+}
+
+/// Template ids that `RithmicReceiverApi` can decode, in ascending order
+pub fn supported_response_templates() -> &'static [u16] {
+    RESPONSE_TEMPLATES
+}
+
+/// Decode a frame captured as hex, e.g. the `data` of a `CapturedFrame`, with or without its
+/// 4 byte length header. Whitespace between the digits is ignored.
+pub fn decode_hex(frame: &str) -> Result<RithmicResponse, String> {
+    let digits: Vec<u8> = frame.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+
+    if !digits.len().is_multiple_of(2) {
+        return Err("hex frame has an odd number of digits".to_string());
+    }
+
+    let data = digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| "hex frame has a non hex digit".to_string())?;
+
+    decode_frame(data)
+}
+
+/// Decode a frame captured as base64, with or without its 4 byte length header
+pub fn decode_base64(frame: &str) -> Result<RithmicResponse, String> {
+    let data = BASE64_STANDARD
+        .decode(frame.trim())
+        .map_err(|e| format!("invalid base64 frame: {}", e))?;
+
+    decode_frame(data)
+}
+
+fn decode_frame(data: Vec<u8>) -> Result<RithmicResponse, String> {
+    let has_header = data.len() >= 4
+        && u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize == data.len() - 4;
+
+    let data = if has_header {
+        Bytes::from(data)
+    } else {
+        let mut framed = BytesMut::with_capacity(data.len() + 4);
+        framed.put_u32(data.len() as u32);
+        framed.extend_from_slice(&data);
+
+        framed.freeze()
+    };
+
+    let receiver_api = RithmicReceiverApi {
+        source: "decode".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    receiver_api.buf_to_message(data)
+}
+
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RithmicConnectionInfo {
     pub url: String,
+    /// Gateways tried in order when `url` can't be reached, e.g. the other uris returned by
+    /// `RithmicSharedPlant::gateway_uris`
+    #[serde(default)]
+    pub fallback_urls: Vec<String>,
+    /// Extra headers sent with the websocket handshake, e.g. `Authorization` for a gateway
+    /// behind an auth proxy
+    #[serde(default)]
+    pub ws_headers: HashMap<String, String>,
     pub user: String,
     pub password: String,
     pub system_name: String,
+    /// Number of raw frames each plant keeps for debugging, 0 disables the capture
+    #[serde(default)]
+    pub capture_raw_frames: usize,
+    /// Log out each plant after this many seconds without a request from the user,
+    /// heartbeats don't count as activity. 0 disables the auto logout
+    #[serde(default)]
+    pub auto_logout_after_secs: u64,
+    /// Number of times idempotent reads are re-sent after reconnecting when the connection is
+    /// lost before their response arrives. Order requests are never re-sent. 0 disables
+    #[serde(default)]
+    pub read_retries: usize,
+    /// Capacity of each plant's update channel. The plant never blocks on it: a handle reading
+    /// slower than updates arrive skips the oldest ones and gets `RecvError::Lagged`
+    #[serde(default = "default_update_buffer_size")]
+    pub update_buffer_size: usize,
+    /// Protocol version sent on login, `TEMPLATE_VERSION` when not set
+    #[serde(default)]
+    pub template_version: Option<String>,
+    /// Seconds between two samples of the account balance kept by the pnl plant for
+    /// `RithmicPnlPlantHandle::equity_curve`. 0 disables the sampling
+    #[serde(default)]
+    pub equity_sample_interval_secs: u64,
+    /// Number of account balance samples kept, the oldest are dropped first
+    #[serde(default = "default_equity_curve_size")]
+    pub equity_curve_size: usize,
+    /// (exchange, symbol pattern) pairs the ticker plant may subscribe to, any other market
+    /// data subscription is refused without being sent. Patterns take `*` and `?` wildcards,
+    /// e.g. ("CME", "ES*"). Empty allows every subscription
+    #[serde(default)]
+    pub subscription_allowlist: Vec<(String, String)>,
+    /// What a plant does once no handle is left to receive its updates
+    #[serde(default)]
+    pub unheard_updates: UnheardUpdates,
+}
+
+fn default_update_buffer_size() -> usize {
+    1024
+}
+
+fn default_equity_curve_size() -> usize {
+    1440
+}
+
+/// `*` matches any run of characters, `?` a single one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // Position after the last `*` and the text position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Clone)]
+pub struct RithmicCredentials {
+    pub user: String,
+    pub password: String,
+    /// Gateway region to prefer, see `RithmicSharedPlant::gateway_uris_in_region`
+    pub region: Option<GatewayRegion>,
+}
+
+/// What a plant does with its updates once every handle and update receiver has been dropped.
+/// A handle that is kept but never read doesn't count: its receiver lags, the plant never waits
+/// for it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnheardUpdates {
+    /// Keep the session and drop the updates until a receiver subscribes again
+    #[default]
+    Discard,
+    /// Close the plant's connection
+    Close,
+}
+
+/// Region of a Rithmic gateway, recognized from the labels of its host name. The known tokens
+/// are `chicago`/`chi` for Chicago, `europe`/`eu`/`de`/`frankfurt`/`ie` for Europe and
+/// `asia`/`sg`/`hk`/`tokyo`/`seoul` for Asia, e.g. a host named `gateway-de.example.com` is
+/// matched as Europe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GatewayRegion {
+    Chicago,
+    Europe,
+    Asia,
+}
+
+impl GatewayRegion {
+    pub fn tokens(&self) -> &'static [&'static str] {
+        match self {
+            GatewayRegion::Chicago => &["chicago", "chi"],
+            GatewayRegion::Europe => &["europe", "eu", "de", "frankfurt", "ie"],
+            GatewayRegion::Asia => &["asia", "sg", "hk", "tokyo", "seoul"],
+        }
+    }
+
+    /// Whether one of the `.` or `-` separated labels of the host of `uri` is a token of
+    /// the region
+    pub fn matches(&self, uri: &str) -> bool {
+        let host = uri
+            .split("://")
+            .last()
+            .unwrap_or(uri)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        host.split(['.', '-'])
+            .any(|label| self.tokens().contains(&label))
+    }
+
+    /// Move the uris of the region first, keeping the server order otherwise. The first uri is
+    /// kept in front when none matches.
+    pub fn prefer(&self, uris: Vec<String>) -> Vec<String> {
+        let (mut preferred, others): (Vec<String>, Vec<String>) =
+            uris.into_iter().partition(|uri| self.matches(uri));
+
+        preferred.extend(others);
+        preferred
+    }
+}
+
+/// Called before each login to get the current credentials, e.g. from a secret store rotating
+/// the password
+pub type CredentialsProvider = Arc<dyn Fn() -> RithmicCredentials + Send + Sync>;
+
+impl RithmicConnectionInfo {
+    /// Catch obvious mistakes before connecting: empty credentials, a malformed url or header.
+    /// Use `RithmicSharedPlant::verify_system_name` to check the system name exists.
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [
+            ("user", &self.user),
+            ("password", &self.password),
+            ("system_name", &self.system_name),
+        ] {
+            if value.trim().is_empty() {
+                return Err(format!("invalid connection info: {} is empty", name));
+            }
+        }
+
+        for url in self.gateway_urls() {
+            crate::ws::gateway_request(&url, &self.ws_headers)
+                .map_err(|e| format!("invalid connection info: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether `subscription_allowlist` lets the ticker plant subscribe to `symbol`
+    pub fn allows_subscription(&self, symbol: &str, exchange: &str) -> bool {
+        self.subscription_allowlist.is_empty()
+            || self.subscription_allowlist.iter().any(|(allowed_exchange, pattern)| {
+                glob_match(allowed_exchange, exchange) && glob_match(pattern, symbol)
+            })
+    }
+
+    /// `url` followed by the fallback urls, without duplicates
+    pub fn gateway_urls(&self) -> Vec<String> {
+        let mut urls = vec![self.url.clone()];
+
+        for url in &self.fallback_urls {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+
+        urls
+    }
 }
 
 impl Default for RithmicConnectionInfo {
     fn default() -> RithmicConnectionInfo {
         RithmicConnectionInfo {
             url: DEFAULT_RTI_WS_URL.to_string(),
+            fallback_urls: vec![],
+            ws_headers: HashMap::new(),
             user: "".to_string(),
             password: "".to_string(),
             system_name: "".to_string(),
+            capture_raw_frames: 0,
+            auto_logout_after_secs: 0,
+            read_retries: 0,
+            update_buffer_size: default_update_buffer_size(),
+            template_version: None,
+            equity_sample_interval_secs: 0,
+            equity_curve_size: default_equity_curve_size(),
+            subscription_allowlist: vec![],
+            unheard_updates: UnheardUpdates::default(),
         }
 
     }
@@ -0,0 +1,84 @@
+use std::{
+    collections::VecDeque,
+    io::Cursor,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use bytes::Bytes;
+use prost::Message;
+
+use crate::rti::MessageType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub timestamp: SystemTime,
+    pub direction: FrameDirection,
+    pub plant: String,
+    pub template_id: i32,
+    /// Raw frame including the 4 byte length header
+    pub data: Bytes,
+}
+
+/// Ring buffer keeping the last frames sent and received by a plant.
+/// Enabled by setting `RithmicConnectionInfo::capture_raw_frames` to the number of frames to keep.
+#[derive(Debug, Clone)]
+pub struct FrameCapture {
+    capacity: usize,
+    frames: Arc<Mutex<VecDeque<CapturedFrame>>>,
+    plant: String,
+}
+
+impl FrameCapture {
+    pub fn new(plant: &str, capacity: usize) -> Self {
+        FrameCapture {
+            capacity,
+            frames: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            plant: plant.to_string(),
+        }
+    }
+
+    /// Returns None when capture is disabled, i.e. a capacity of 0
+    pub fn from_capacity(plant: &str, capacity: usize) -> Option<Self> {
+        if capacity == 0 {
+            None
+        } else {
+            Some(FrameCapture::new(plant, capacity))
+        }
+    }
+
+    pub fn record(&self, direction: FrameDirection, data: &Bytes) {
+        let template_id = data
+            .get(4..)
+            .and_then(|payload| MessageType::decode(&mut Cursor::new(payload)).ok())
+            .map(|m| m.template_id)
+            .unwrap_or_default();
+
+        let frame = CapturedFrame {
+            timestamp: SystemTime::now(),
+            direction,
+            plant: self.plant.clone(),
+            template_id,
+            data: data.clone(),
+        };
+
+        let mut frames = self.frames.lock().unwrap();
+
+        if frames.len() == self.capacity {
+            frames.pop_front();
+        }
+
+        frames.push_back(frame);
+    }
+
+    /// Captured frames, oldest first
+    pub fn recent_frames(&self) -> Vec<CapturedFrame> {
+        self.frames.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -1,89 +1,169 @@
-use std::io::Cursor;
 use bytes::Bytes;
 use crate::rti::{messages::RithmicMessage, *};
+use super::{
+    frame_capture::{FrameCapture, FrameDirection},
+    templates::*,
+    TEMPLATE_VERSION,
+};
 use prost::Message;
 use tracing::{event, Level};
 
 #[derive(Debug, Clone)]
 pub struct RithmicResponse {
+    /// `user_msg` of the request this answers, empty for updates
     pub request_id: String,
     pub message: RithmicMessage,
+    /// Pushed by the server rather than answering a request, e.g. a `LastTrade`, as opposed to
+    /// the `ResponseMarketDataUpdate` answering the subscription. Decides the routing, see
+    /// `RithmicRequestHandler::dispatch`
     pub is_update: bool,
     pub has_more: bool,
     pub multi_response: bool,
     pub error: Option<String>,
     pub source: String,
+    /// Full `rp_code` of the response, also on success. Empty for updates, which carry none
+    pub response_code: ResponseCode,
+}
+
+/// `rp_code` of a response: its code, "0" on success, then a message and any extra fields
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseCode {
+    pub code: String,
+    pub message: Option<String>,
+    pub extra: Vec<String>,
+}
+
+impl ResponseCode {
+    pub fn from_rp_code(rp_code: &[String]) -> Self {
+        let mut fields = rp_code.iter().cloned();
+
+        ResponseCode {
+            code: fields.next().unwrap_or_default(),
+            message: fields.next(),
+            extra: fields.collect(),
+        }
+    }
+
+    /// No code, as on updates, or "0"
+    pub fn is_success(&self) -> bool {
+        self.code.is_empty() || self.code == "0"
+    }
+}
+
+impl RithmicResponse {
+    /// Second `user_msg` entry echoed by the server, see `RithmicOrder::correlation`. Order
+    /// notifications don't carry `user_msg`, they are matched on their `user_tag`.
+    pub fn correlation(&self) -> Option<&str> {
+        match &self.message {
+            RithmicMessage::ResponseNewOrder(resp) => resp.user_msg.get(1).map(String::as_str),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct RithmicReceiverApi {
     pub source: String,
+    pub frame_capture: Option<FrameCapture>,
+    /// Protocol version sent on login, the gateway's is checked against it.
+    /// `TEMPLATE_VERSION` when not set, as for `RithmicConnectionInfo::template_version`
+    pub template_version: Option<String>,
 }
 
 impl RithmicReceiverApi {
     pub fn buf_to_message(&self, data: Bytes) -> Result<RithmicResponse, String> {
-        let parsed_message = MessageType::decode(&mut Cursor::new(&data[4..]));
+        if let Some(frame_capture) = &self.frame_capture {
+            frame_capture.record(FrameDirection::Inbound, &data);
+        }
+
+        let payload = data
+            .get(4..)
+            .ok_or_else(|| "frame shorter than its length header".to_string())?;
 
-        let response = match parsed_message.clone().unwrap().template_id {
-            11 => {
-                let resp = ResponseLogin::decode(&mut Cursor::new(&data[4..])).unwrap();
+        let template_id = decode::<MessageType>(payload)?.template_id;
+
+        let response = match template_id {
+            RESPONSE_LOGIN => {
+                let resp = decode::<ResponseLogin>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                let expected = self.template_version.as_deref().unwrap_or(TEMPLATE_VERSION);
+
+                if let Some(version) = resp.template_version.as_deref()
+                    && version != expected
+                {
+                    event!(
+                        Level::WARN,
+                        "receiver_api: gateway protocol version {} differs from {}, messages may not decode as expected",
+                        version,
+                        expected
+                    );
+                }
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseLogin(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            13 => {
-                let resp = ResponseLogout::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_LOGOUT => {
+                let resp = decode::<ResponseLogout>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseLogout(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            15 => {
-                let resp = ResponseReferenceData::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_REFERENCE_DATA => {
+                let resp = decode::<ResponseReferenceData>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseReferenceData(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            17 => {
-                let resp = ResponseRithmicSystemInfo::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_RITHMIC_SYSTEM_INFO => {
+                let resp = decode::<ResponseRithmicSystemInfo>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseRithmicSystemInfo(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            19 => {
-                let resp = ResponseHeartbeat::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_HEARTBEAT => {
+                let resp = decode::<ResponseHeartbeat>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -93,38 +173,43 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            21 => {
-                let resp = ResponseRithmicSystemGatewayInfo::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_RITHMIC_SYSTEM_GATEWAY_INFO => {
+                let resp = decode::<ResponseRithmicSystemGatewayInfo>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseRithmicSystemGatewayInfo(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            75 => {
-                let resp = Reject::decode(&mut Cursor::new(&data[4..])).unwrap();
+            REJECT => {
+                let resp = decode::<Reject>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::Reject(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            77 => {
-                let resp = ForcedLogout::decode(&mut Cursor::new(&data[4..])).unwrap();
+            FORCED_LOGOUT => {
+                let resp = decode::<ForcedLogout>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -134,69 +219,95 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: Some("forced logout from server".to_string()),
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            101 => {
-                let resp = ResponseMarketDataUpdate::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_MARKET_DATA_UPDATE => {
+                let resp = decode::<ResponseMarketDataUpdate>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseMarketDataUpdate(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            103 => {
-                let resp = ResponseGetInstrumentByUnderlying::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_GET_INSTRUMENT_BY_UNDERLYING => {
+                let resp = decode::<ResponseGetInstrumentByUnderlying>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseGetInstrumentByUnderlying(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            110 => {
-                let resp = ResponseSearchSymbols::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_SEARCH_SYMBOLS => {
+                let resp = decode::<ResponseSearchSymbols>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseSearchSymbols(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            112 => {
-                let resp = ResponseProductCodes::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_PRODUCT_CODES => {
+                let resp = decode::<ResponseProductCodes>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseProductCodes(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
+                }
+            }
+            RESPONSE_FRONT_MONTH_CONTRACT => {
+                let resp =
+                    decode::<ResponseFrontMonthContract>(payload)?;
+                let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                RithmicResponse {
+                    request_id: request_id(&resp.user_msg),
+                    message: RithmicMessage::ResponseFrontMonthContract(resp),
+                    is_update: false,
+                    has_more: false,
+                    multi_response: false,
+                    error,
+                    source: self.source.clone(),
+                    response_code,
                 }
             }
-            150 => {
-                let resp = LastTrade::decode(&mut Cursor::new(&data[4..])).unwrap();
+            LAST_TRADE => {
+                let resp = decode::<LastTrade>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -206,10 +317,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            151 => {
-                let resp = BestBidOffer::decode(&mut Cursor::new(&data[4..])).unwrap();
+            BEST_BID_OFFER => {
+                let resp = decode::<BestBidOffer>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -219,10 +331,53 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
+                }
+            }
+            END_OF_DAY_PRICES => {
+                let resp = decode::<EndOfDayPrices>(payload)?;
+
+                RithmicResponse {
+                    request_id: "".to_string(),
+                    message: RithmicMessage::EndOfDayPrices(resp),
+                    is_update: true,
+                    has_more: false,
+                    multi_response: false,
+                    error: None,
+                    source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            156 => {
-                let resp = OrderBook::decode(&mut Cursor::new(&data[4..])).unwrap();
+            MARKET_MODE => {
+                let resp = decode::<MarketMode>(payload)?;
+
+                RithmicResponse {
+                    request_id: "".to_string(),
+                    message: RithmicMessage::MarketMode(resp),
+                    is_update: true,
+                    has_more: false,
+                    multi_response: false,
+                    error: None,
+                    source: self.source.clone(),
+                    response_code: ResponseCode::default(),
+                }
+            }
+            SYMBOL_MARGIN_RATE => {
+                let resp = decode::<SymbolMarginRate>(payload)?;
+
+                RithmicResponse {
+                    request_id: "".to_string(),
+                    message: RithmicMessage::SymbolMarginRate(resp),
+                    is_update: true,
+                    has_more: false,
+                    multi_response: false,
+                    error: None,
+                    source: self.source.clone(),
+                    response_code: ResponseCode::default(),
+                }
+            }
+            ORDER_BOOK => {
+                let resp = decode::<OrderBook>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -232,10 +387,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            158 => {
-                let resp = OpenInterest::decode(&mut Cursor::new(&data[4..])).unwrap();
+            OPEN_INTEREST => {
+                let resp = decode::<OpenInterest>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -245,10 +401,26 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
+                }
+            }
+            FRONT_MONTH_CONTRACT_UPDATE => {
+                let resp =
+                    decode::<FrontMonthContractUpdate>(payload)?;
+
+                RithmicResponse {
+                    request_id: "".to_string(),
+                    message: RithmicMessage::FrontMonthContractUpdate(resp),
+                    is_update: true,
+                    has_more: false,
+                    multi_response: false,
+                    error: None,
+                    source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            160 => {
-                let resp = DepthByOrder::decode(&mut Cursor::new(&data[4..])).unwrap();
+            DEPTH_BY_ORDER => {
+                let resp = decode::<DepthByOrder>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -258,83 +430,112 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            201 => {
-                let resp = ResponseTimeBarUpdate::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_TIME_BAR_UPDATE => {
+                let resp = decode::<ResponseTimeBarUpdate>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseTimeBarUpdate(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            203 => {
-                let resp = ResponseTimeBarReplay::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_TIME_BAR_REPLAY => {
+                let resp = decode::<ResponseTimeBarReplay>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseTimeBarReplay(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            205 => {
-                let resp = ResponseTickBarUpdate::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_TICK_BAR_UPDATE => {
+                let resp = decode::<ResponseTickBarUpdate>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseTickBarUpdate(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            207 => {
-                let resp = ResponseTickBarReplay::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_TICK_BAR_REPLAY => {
+                let resp = decode::<ResponseTickBarReplay>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseTickBarReplay(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            209 => {
-                let resp = ResponseVolumeProfileMinuteBars::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_VOLUME_PROFILE_MINUTE_BARS => {
+                let resp = decode::<ResponseVolumeProfileMinuteBars>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseVolumeProfileMinuteBars(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
+                }
+            }
+            RESPONSE_RESUME_BARS => {
+                let resp = decode::<ResponseResumeBars>(payload)?;
+                let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                // Only acknowledges the resume, the bars follow as parts of the same request.
+                // A rejection ends it.
+                RithmicResponse {
+                    request_id: request_id(&resp.user_msg),
+                    message: RithmicMessage::ResponseResumeBars(resp),
+                    is_update: false,
+                    has_more: error.is_none(),
+                    multi_response: true,
+                    error,
+                    source: self.source.clone(),
+                    response_code,
                 }
             }
-            250 => {
-                let resp = TimeBar::decode(&mut Cursor::new(&data[4..])).unwrap();
+            TIME_BAR => {
+                let resp = decode::<TimeBar>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -344,10 +545,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            251 => {
-                let resp = TickBar::decode(&mut Cursor::new(&data[4..])).unwrap();
+            TICK_BAR => {
+                let resp = decode::<TickBar>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -357,308 +559,365 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            303 => {
-                let resp = ResponseAccountList::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_ACCOUNT_LIST => {
+                let resp = decode::<ResponseAccountList>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseAccountList(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            305 => {
-                let resp = ResponseAccountRmsInfo::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_ACCOUNT_RMS_INFO => {
+                let resp = decode::<ResponseAccountRmsInfo>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseAccountRmsInfo(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            307 => {
-                let resp = ResponseProductRmsInfo::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_PRODUCT_RMS_INFO => {
+                let resp = decode::<ResponseProductRmsInfo>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseProductRmsInfo(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            309 => {
+            RESPONSE_SUBSCRIBE_FOR_ORDER_UPDATES => {
                 let resp =
-                    ResponseSubscribeForOrderUpdates::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseSubscribeForOrderUpdates>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseSubscribeForOrderUpdates(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            311 => {
-                let resp = ResponseTradeRoutes::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_TRADE_ROUTES => {
+                let resp = decode::<ResponseTradeRoutes>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseTradeRoutes(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            313 => {
-                let resp = ResponseNewOrder::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_NEW_ORDER => {
+                let resp = decode::<ResponseNewOrder>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseNewOrder(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            315 => {
-                let resp = ResponseModifyOrder::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_MODIFY_ORDER => {
+                let resp = decode::<ResponseModifyOrder>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseModifyOrder(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            317 => {
-                let resp = ResponseCancelOrder::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_CANCEL_ORDER => {
+                let resp = decode::<ResponseCancelOrder>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseCancelOrder(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            319 => {
+            RESPONSE_SHOW_ORDER_HISTORY_DATES => {
                 let resp =
-                    ResponseShowOrderHistoryDates::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseShowOrderHistoryDates>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowOrderHistoryDates(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            321 => {
-                let resp = ResponseShowOrders::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_SHOW_ORDERS => {
+                let resp = decode::<ResponseShowOrders>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowOrders(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            323 => {
-                let resp = ResponseShowOrderHistory::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_SHOW_ORDER_HISTORY => {
+                let resp = decode::<ResponseShowOrderHistory>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowOrderHistory(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            325 => {
+            RESPONSE_SHOW_ORDER_HISTORY_SUMMARY => {
                 let resp =
-                    ResponseShowOrderHistorySummary::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseShowOrderHistorySummary>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowOrderHistorySummary(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            327 => {
+            RESPONSE_SHOW_ORDER_HISTORY_DETAIL => {
                 let resp =
-                    ResponseShowOrderHistoryDetail::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseShowOrderHistoryDetail>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowOrderHistoryDetail(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            331 => {
-                let resp = ResponseBracketOrder::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_BRACKET_ORDER => {
+                let resp = decode::<ResponseBracketOrder>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let error = self.get_error(&resp.rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseBracketOrder(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            333 => {
+            RESPONSE_UPDATE_TARGET_BRACKET_LEVEL => {
                 let resp =
-                    ResponseUpdateTargetBracketLevel::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseUpdateTargetBracketLevel>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseUpdateTargetBracketLevel(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            335 => {
+            RESPONSE_UPDATE_STOP_BRACKET_LEVEL => {
                 let resp =
-                    ResponseUpdateStopBracketLevel::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseUpdateStopBracketLevel>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseUpdateStopBracketLevel(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            337 => {
-                let resp = ResponseSubscribeToBracketUpdates::decode(&mut Cursor::new(&data[4..]))
-                    .unwrap();
+            RESPONSE_SUBSCRIBE_TO_BRACKET_UPDATES => {
+                let resp = decode::<ResponseSubscribeToBracketUpdates>(payload)?;
                 let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseSubscribeToBracketUpdates(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            339 => {
-                let resp = ResponseShowBrackets::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_SHOW_BRACKETS => {
+                let resp = decode::<ResponseShowBrackets>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let err = self.get_error(&resp.rp_code);
+                let err = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowBrackets(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            341 => {
-                let resp = ResponseShowBracketStops::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_SHOW_BRACKET_STOPS => {
+                let resp = decode::<ResponseShowBracketStops>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let err = self.get_error(&resp.rp_code);
+                let err = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseShowBracketStops(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
+                }
+            }
+            RESPONSE_LIST_EXCHANGE_PERMISSIONS => {
+                let resp = decode::<ResponseListExchangePermissions>(payload)?;
+                let has_more = self.has_multiple(&resp.rq_handler_rp_code);
+                let error = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                RithmicResponse {
+                    request_id: request_id(&resp.user_msg),
+                    message: RithmicMessage::ResponseListExchangePermissions(resp),
+                    is_update: false,
+                    has_more,
+                    multi_response: true,
+                    error,
+                    source: self.source.clone(),
+                    response_code,
                 }
             }
-            347 => {
-                let resp = ResponseCancelAllOrders::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_CANCEL_ALL_ORDERS => {
+                let resp = decode::<ResponseCancelAllOrders>(payload)?;
                 let err = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseCancelAllOrders(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            351 => {
-                let resp = RithmicOrderNotification::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RITHMIC_ORDER_NOTIFICATION => {
+                let resp = decode::<RithmicOrderNotification>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -668,10 +927,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            352 => {
-                let resp = ExchangeOrderNotification::decode(&mut Cursor::new(&data[4..])).unwrap();
+            EXCHANGE_ORDER_NOTIFICATION => {
+                let resp = decode::<ExchangeOrderNotification>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -681,10 +941,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            353 => {
-                let resp = BracketUpdates::decode(&mut Cursor::new(&data[4..])).unwrap();
+            BRACKET_UPDATES => {
+                let resp = decode::<BracketUpdates>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -694,56 +955,110 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
+                }
+            }
+            ACCOUNT_RMS_UPDATES => {
+                let resp = decode::<AccountRmsUpdates>(payload)?;
+
+                RithmicResponse {
+                    request_id: "".to_string(),
+                    message: RithmicMessage::AccountRmsUpdates(resp),
+                    is_update: true,
+                    has_more: false,
+                    multi_response: false,
+                    error: None,
+                    source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            3505 => {
-                let resp = ResponseExitPosition::decode(&mut Cursor::new(&data[4..])).unwrap();
+            RESPONSE_EXIT_POSITION => {
+                let resp = decode::<ResponseExitPosition>(payload)?;
                 let has_more = self.has_multiple(&resp.rq_handler_rp_code);
-                let err = self.get_error(&resp.rp_code);
+                let err = self.get_stream_error(&resp.rp_code, &resp.rq_handler_rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponseExitPosition(resp),
                     is_update: false,
                     has_more,
                     multi_response: true,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            401 => {
+            RESPONSE_REPLAY_EXECUTIONS => {
+                let resp = decode::<ResponseReplayExecutions>(payload)?;
+                let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                RithmicResponse {
+                    request_id: request_id(&resp.user_msg),
+                    message: RithmicMessage::ResponseReplayExecutions(resp),
+                    is_update: false,
+                    has_more: false,
+                    multi_response: false,
+                    error,
+                    source: self.source.clone(),
+                    response_code,
+                }
+            }
+            RESPONSE_ACCOUNT_RMS_UPDATES => {
                 let resp =
-                    ResponsePnLPositionUpdates::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponseAccountRmsUpdates>(payload)?;
+                let error = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
+
+                RithmicResponse {
+                    request_id: request_id(&resp.user_msg),
+                    message: RithmicMessage::ResponseAccountRmsUpdates(resp),
+                    is_update: false,
+                    has_more: false,
+                    multi_response: false,
+                    error,
+                    source: self.source.clone(),
+                    response_code,
+                }
+            }
+            RESPONSE_PNL_POSITION_UPDATES => {
+                let resp =
+                    decode::<ResponsePnLPositionUpdates>(payload)?;
                 let err = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponsePnLPositionUpdates(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            403 => {
+            RESPONSE_PNL_POSITION_SNAPSHOT => {
                 let resp =
-                    ResponsePnLPositionSnapshot::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<ResponsePnLPositionSnapshot>(payload)?;
                 let err = self.get_error(&resp.rp_code);
+                let response_code = ResponseCode::from_rp_code(&resp.rp_code);
 
                 RithmicResponse {
-                    request_id: resp.user_msg[0].clone(),
+                    request_id: request_id(&resp.user_msg),
                     message: RithmicMessage::ResponsePnLPositionSnapshot(resp),
                     is_update: false,
                     has_more: false,
                     multi_response: false,
                     error: err,
                     source: self.source.clone(),
+                    response_code,
                 }
             }
-            450 => {
+            INSTRUMENT_PNL_POSITION_UPDATE => {
                 let resp =
-                    InstrumentPnLPositionUpdate::decode(&mut Cursor::new(&data[4..])).unwrap();
+                    decode::<InstrumentPnLPositionUpdate>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -753,10 +1068,11 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            451 => {
-                let resp = AccountPnLPositionUpdate::decode(&mut Cursor::new(&data[4..])).unwrap();
+            ACCOUNT_PNL_POSITION_UPDATE => {
+                let resp = decode::<AccountPnLPositionUpdate>(payload)?;
 
                 RithmicResponse {
                     request_id: "".to_string(),
@@ -766,13 +1082,31 @@ impl RithmicReceiverApi {
                     multi_response: false,
                     error: None,
                     source: self.source.clone(),
+                    response_code: ResponseCode::default(),
                 }
             }
-            _ => {
-                panic!("Unknown message type: {:#01x?}", parsed_message)
+            // Requests (e.g. an echoed RequestLogin, template 10) and templates we don't
+            // decode yet are reported instead of being mapped to a fabricated response
+            template_id => {
+                event!(
+                    Level::WARN,
+                    "receiver_api: unhandled template {}",
+                    template_id
+                );
+
+                return Err(format!("unhandled template id {}", template_id));
             }
         };
 
+        event!(
+            Level::TRACE,
+            source = %self.source,
+            template_id,
+            request_id = %response.request_id,
+            is_update = response.is_update,
+            "receiver_api: decoded message"
+        );
+
         // Handle errors
         let err = self.check_message_error(&response);
 
@@ -784,7 +1118,11 @@ impl RithmicReceiverApi {
                 error
             );
 
-            return Err(error);
+            // Responses to a request keep their error so the request handler can hand it to
+            // the caller waiting for them, including a stream ended midway
+            if response.is_update || response.request_id.is_empty() {
+                return Err(error);
+            }
         }
 
         Ok(response)
@@ -795,12 +1133,34 @@ impl RithmicReceiverApi {
     }
 
     fn get_error(&self, rp_code: &Vec<String>) -> Option<String> {
-        if (rp_code.len() == 1 && rp_code[0] == "0") || (rp_code.is_empty()) {
+        let response_code = ResponseCode::from_rp_code(rp_code);
+
+        if response_code.is_success() {
             None
         } else {
             event!(Level::ERROR, "receiver_api: error {:#?}", rp_code);
 
-            Some(rp_code[1].clone())
+            // Some rejects carry their code alone
+            Some(
+                response_code
+                    .message
+                    .unwrap_or_else(|| format!("rejected with code {}", response_code.code)),
+            )
+        }
+    }
+
+    /// Error of a part of a multi-response: a non zero `rq_handler_rp_code` ends the stream
+    /// early, the error then comes with it instead of in `rp_code`
+    fn get_stream_error(&self, rp_code: &Vec<String>, rq_handler_rp_code: &[String]) -> Option<String> {
+        match rq_handler_rp_code {
+            [] => self.get_error(rp_code),
+            [code] if code == "0" => self.get_error(rp_code),
+            [code] => Some(format!("stream ended with code {}", code)),
+            [_, message, ..] => {
+                event!(Level::ERROR, "receiver_api: stream error {:#?}", rq_handler_rp_code);
+
+                Some(message.clone())
+            }
         }
     }
 
@@ -808,3 +1168,14 @@ impl RithmicReceiverApi {
         message.error.as_ref().map(|e| e.to_string())
     }
 }
+
+/// Decode the message of a frame, failing instead of panicking on a malformed one
+fn decode<M: Message + Default>(payload: &[u8]) -> Result<M, String> {
+    M::decode(payload).map_err(|e| format!("undecodable frame: {}", e))
+}
+
+/// `request_id` of a response: the first `user_msg` of the request it answers. Empty when the
+/// request was sent without one, e.g. `RequestGetInstrumentByUnderlying`
+fn request_id(user_msg: &[String]) -> String {
+    user_msg.first().cloned().unwrap_or_default()
+}
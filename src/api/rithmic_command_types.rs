@@ -1,6 +1,48 @@
+use std::{
+    fmt,
+    ops::BitOr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::api::receiver_api::RithmicResponse;
+use crate::rti::{
+    best_bid_offer::PresenceBits, exchange_order_notification::TransactionType,
+    request_market_data_update::UpdateBits, request_modify_order, request_new_order,
+    request_search_symbols, request_tick_bar_replay, request_time_bar_replay,
+    AccountRmsUpdates, BestBidOffer, ExchangeOrderNotification, ResponseNewOrder,
+    ResponseReferenceData, ResponseTradeRoutes, RithmicOrderNotification,
+};
+
+/// Whether orders are placed by a person or by an automated system. Rithmic requires every
+/// order, modify and cancel to be tagged correctly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OrderPlacement {
+    Manual,
+    #[default]
+    Auto,
+}
+
+impl OrderPlacement {
+    /// The per-order `auto` flag when set, else this default
+    pub fn resolve(self, auto: Option<bool>) -> request_new_order::OrderPlacement {
+        let placement = match auto {
+            Some(true) => OrderPlacement::Auto,
+            Some(false) => OrderPlacement::Manual,
+            None => self,
+        };
+
+        match placement {
+            OrderPlacement::Manual => request_new_order::OrderPlacement::Manual,
+            OrderPlacement::Auto => request_new_order::OrderPlacement::Auto,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RithmicBracketOrder {
     pub action: i32,
+    /// Overrides the plant's `OrderPlacement` when set
+    pub auto: Option<bool>,
     pub duration: i32,
     pub exchange: String,
     pub localid: String,
@@ -10,19 +52,836 @@ pub struct RithmicBracketOrder {
     pub qty: i32,
     pub stop_ticks: i32,
     pub symbol: String,
+    /// Trigger price of the entry, only for stop and if touched orders
+    pub trigger_price: Option<f64>,
+}
+
+/// Child order of a bracket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BracketLeg {
+    Target,
+    Stop,
+}
+
+#[derive(Debug, Clone)]
+pub struct RithmicOrder {
+    /// Account the order is booked to instead of the selected one, e.g. a sub-account of a
+    /// multi-level account structure. The protocol has no separate sub-account field, the
+    /// full account id as known by the FCM goes here.
+    pub account_id: Option<String>,
+    pub action: request_new_order::TransactionType,
+    /// Overrides the plant's `OrderPlacement` when set
+    pub auto: Option<bool>,
+    /// Good till date: seconds since epoch at which the server cancels the order.
+    /// Only valid with a GTC duration, which is used when `duration` is None.
+    pub cancel_at_ssboe: Option<i32>,
+    /// Opaque tag sent as a second `user_msg` entry, e.g. a strategy id. The server echoes it on
+    /// the response to the order, see `RithmicResponse::correlation`.
+    pub correlation: Option<String>,
+    pub duration: Option<request_new_order::Duration>,
+    pub exchange: String,
+    pub localid: String,
+    pub ordertype: request_new_order::PriceType,
+    pub price: f64,
+    /// Number of contracts, see `quantity_in_lots` for products traded in lots
+    pub qty: i32,
+    pub symbol: String,
+    /// Trigger price, only for stop and if touched orders
+    pub trigger_price: Option<f64>,
+}
+
+impl RithmicOrder {
+    /// Checks the quantity is positive, the duration and cancel time are consistent, and that
+    /// stop and if touched orders, and only them, have a trigger price before the order is sent
+    pub fn validate(&self) -> Result<(), String> {
+        use request_new_order::{Duration, PriceType};
+
+        if self.qty <= 0 {
+            return Err(format!("order {}: invalid qty {}", self.localid, self.qty));
+        }
+
+        if let (Some(duration), Some(_)) = (self.duration, self.cancel_at_ssboe)
+            && duration != Duration::Gtc
+        {
+            return Err(format!(
+                "order {}: cancel_at_ssboe requires a GTC duration, got {:?}",
+                self.localid, duration
+            ));
+        }
+
+        let takes_trigger = !matches!(self.ordertype, PriceType::Limit | PriceType::Market);
+
+        match (takes_trigger, self.trigger_price) {
+            (true, None) => Err(format!(
+                "order {}: {:?} requires a trigger_price",
+                self.localid, self.ordertype
+            )),
+            (false, Some(_)) => Err(format!(
+                "order {}: {:?} can't have a trigger_price",
+                self.localid, self.ordertype
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks `qty` is a whole number of lots of `lot_size` contracts. The reference data of
+    /// the protocol has no lot size, it comes from the product specification.
+    pub fn validate_lot_size(&self, lot_size: i32) -> Result<(), String> {
+        if lot_size <= 0 {
+            return Err(format!("order {}: invalid lot size {}", self.localid, lot_size));
+        }
+
+        if self.qty % lot_size != 0 {
+            return Err(format!(
+                "order {}: qty {} is not a multiple of the lot size {}",
+                self.localid, self.qty, lot_size
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of contracts in `lots` lots of `lot_size` contracts, for `RithmicOrder::qty`
+pub fn quantity_in_lots(lots: i32, lot_size: i32) -> Result<i32, String> {
+    if lots <= 0 || lot_size <= 0 {
+        return Err(format!("invalid quantity: {} lots of {}", lots, lot_size));
+    }
+
+    lots.checked_mul(lot_size)
+        .ok_or_else(|| format!("invalid quantity: {} lots of {} overflows", lots, lot_size))
 }
 
 #[derive(Debug, Clone)]
 pub struct RithmicModifyOrder {
     pub id: String,
+    /// Overrides the plant's `OrderPlacement` when set
+    pub auto: Option<bool>,
     pub exchange: String,
     pub symbol: String,
     pub qty: i32,
-    pub price: f64,
+    /// Limit price, only for limit, stop limit and limit if touched orders
+    pub price: Option<f64>,
+    /// Trigger price, only for stop and if touched orders
+    pub trigger_price: Option<f64>,
+    /// `request_modify_order::PriceType`, may differ from the order's to change its type
     pub ordertype: i32,
 }
 
+impl RithmicModifyOrder {
+    /// Checks the prices match the price type, e.g. a modify to market doesn't carry the
+    /// previous limit price
+    pub fn validate(&self) -> Result<(), String> {
+        use request_modify_order::PriceType;
+
+        let price_type = PriceType::try_from(self.ordertype)
+            .map_err(|_| format!("modify {}: unknown price type {}", self.id, self.ordertype))?;
+
+        let takes_price = matches!(
+            price_type,
+            PriceType::Limit | PriceType::StopLimit | PriceType::LimitIfTouched
+        );
+        let takes_trigger = !matches!(price_type, PriceType::Limit | PriceType::Market);
+
+        for (name, takes, value) in [
+            ("price", takes_price, self.price),
+            ("trigger_price", takes_trigger, self.trigger_price),
+        ] {
+            match (takes, value) {
+                (true, None) => {
+                    return Err(format!("modify {}: {:?} requires a {}", self.id, price_type, name));
+                }
+                (false, Some(_)) => {
+                    return Err(format!("modify {}: {:?} can't have a {}", self.id, price_type, name));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Kind of instrument `RithmicTickerPlantHandle::search_symbols` is restricted to. Mirrors the
+/// protocol's enum so the handle doesn't expose the generated one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstrumentType {
+    Future,
+    FutureOption,
+    FutureStrategy,
+    Equity,
+    EquityOption,
+    EquityStrategy,
+    Index,
+    IndexOption,
+    Spread,
+    Synthetic,
+}
+
+impl From<InstrumentType> for request_search_symbols::InstrumentType {
+    fn from(instrument_type: InstrumentType) -> Self {
+        use request_search_symbols::InstrumentType as Generated;
+
+        match instrument_type {
+            InstrumentType::Future => Generated::Future,
+            InstrumentType::FutureOption => Generated::FutureOption,
+            InstrumentType::FutureStrategy => Generated::FutureStrategy,
+            InstrumentType::Equity => Generated::Equity,
+            InstrumentType::EquityOption => Generated::EquityOption,
+            InstrumentType::EquityStrategy => Generated::EquityStrategy,
+            InstrumentType::Index => Generated::Index,
+            InstrumentType::IndexOption => Generated::IndexOption,
+            InstrumentType::Spread => Generated::Spread,
+            InstrumentType::Synthetic => Generated::Synthetic,
+        }
+    }
+}
+
+/// Outcome of the last market data subscribe request of a symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    Accepted,
+    /// e.g. not entitled to the exchange's market data
+    Rejected { reason: String },
+}
+
+impl SubscriptionStatus {
+    pub fn from_response(response: &RithmicResponse) -> Self {
+        match &response.error {
+            None => SubscriptionStatus::Accepted,
+            Some(reason) => SubscriptionStatus::Rejected {
+                reason: reason.clone(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RithmicCancelOrder {
     pub id: String,
+    /// Overrides the plant's `OrderPlacement` when set
+    pub auto: Option<bool>,
+}
+
+/// A range of tick bars for `RithmicHistoryPlantHandle::get_historical_tick_bar`.
+/// `bar_type_specifier` is the size of the bars, see `validate_bar_type_specifier`
+#[derive(Debug, Clone)]
+pub struct RithmicTickBarRange {
+    pub symbol: String,
+    pub exchange: String,
+    pub bar_type: request_tick_bar_replay::BarType,
+    pub bar_sub_type: request_tick_bar_replay::BarSubType,
+    pub bar_type_specifier: String,
+    pub start_index: i32,
+    pub finish_index: i32,
+}
+
+/// A range of time bars for `RithmicHistoryPlantHandle::get_historical_time_bar` and
+/// `get_historical_time_bar_chunked`. `start_index` and `finish_index` are seconds since the
+/// epoch in UTC, both inclusive.
+#[derive(Debug, Clone)]
+pub struct RithmicTimeBarRange {
+    pub symbol: String,
+    pub exchange: String,
+    pub bar_type: request_time_bar_replay::BarType,
+    pub bar_type_period: i32,
+    pub start_index: i32,
+    pub finish_index: i32,
+}
+
+/// A trade route orders can be sent through, see `RithmicOrderPlantHandle::trade_routes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RithmicTradeRoute {
+    pub fcm_id: Option<String>,
+    pub ib_id: Option<String>,
+    pub exchange: String,
+    pub trade_route: String,
+    pub status: Option<String>,
+    pub is_default: bool,
+}
+
+impl RithmicTradeRoute {
+    pub fn from_response(response: &ResponseTradeRoutes) -> Option<Self> {
+        Some(RithmicTradeRoute {
+            fcm_id: response.fcm_id.clone(),
+            ib_id: response.ib_id.clone(),
+            exchange: response.exchange.clone()?,
+            trade_route: response.trade_route.clone()?,
+            status: response.status.clone(),
+            is_default: response.is_default.unwrap_or(false),
+        })
+    }
+
+    /// Route to use for `exchange`: the one the server marks as default, else the first by name.
+    /// `routes` as returned by `trade_routes`, sorted, so the choice is the same on every run.
+    pub fn preferred<'a>(routes: &'a [RithmicTradeRoute], exchange: &str) -> Option<&'a Self> {
+        let mut routes = routes.iter().filter(|route| route.exchange == exchange);
+        let first = routes.clone().next();
+
+        routes.find(|route| route.is_default).or(first)
+    }
+}
+
+/// An account available to the logged in user. fcm_id and ib_id can differ
+/// between accounts (give-up / omnibus setups), pass all three to `select_account`.
+#[derive(Debug, Clone)]
+pub struct RithmicAccount {
+    pub fcm_id: String,
+    pub ib_id: String,
+    pub account_id: String,
+    pub account_name: Option<String>,
+}
+
+/// Rejection of a new order, `code` and `text` are the rp_code of the response.
+/// Errors that don't come from the server, e.g. no active account, have an empty code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderRejection {
+    pub code: String,
+    pub text: String,
+    pub basket_id: Option<String>,
+}
+
+impl OrderRejection {
+    /// Returns None when the response accepted the order
+    pub fn from_response(response: &ResponseNewOrder) -> Option<Self> {
+        match response.rp_code.as_slice() {
+            [] => None,
+            [code] if code == "0" => None,
+            [code, rest @ ..] => Some(OrderRejection {
+                code: code.clone(),
+                text: rest.join(" "),
+                basket_id: response.basket_id.clone(),
+            }),
+        }
+    }
+}
+
+impl From<String> for OrderRejection {
+    fn from(text: String) -> Self {
+        OrderRejection {
+            code: "".to_string(),
+            text,
+            basket_id: None,
+        }
+    }
+}
+
+impl fmt::Display for OrderRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.code.is_empty() {
+            write!(f, "{}", self.text)
+        } else {
+            write!(f, "order rejected ({}): {}", self.code, self.text)
+        }
+    }
+}
+
+/// Round-trip latencies of the orders placed by this session, from sending the new order
+/// request to the first exchange order notification accepting it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderLatencyStats {
+    pub count: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+}
+
+impl OrderLatencyStats {
+    /// Returns None when there is no latency
+    pub fn from_latencies(latencies: &[Duration]) -> Option<Self> {
+        let min = *latencies.iter().min()?;
+        let max = *latencies.iter().max()?;
+        let total: Duration = latencies.iter().sum();
+
+        Some(OrderLatencyStats {
+            count: latencies.len(),
+            min,
+            mean: total / latencies.len() as u32,
+            max,
+        })
+    }
+}
+
+/// Whether an order reached the exchange, see `RithmicOrderPlantHandle::place_order_confirmed`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderAck {
+    Accepted { basket_id: String },
+    Rejected {
+        basket_id: Option<String>,
+        reason: String,
+    },
+}
+
+impl OrderAck {
+    /// Returns None while the order is still on its way to the exchange. An order completed
+    /// right away, e.g. a filled market order, was accepted: an order the exchange rejects is
+    /// reported by its reject notification first, see `from_exchange_notification`.
+    pub fn from_notification(notification: &RithmicOrderNotification) -> Option<Self> {
+        use crate::rti::rithmic_order_notification::NotifyType;
+
+        let basket_id = notification.basket_id.clone()?;
+
+        match notification.notify_type() {
+            NotifyType::Open | NotifyType::TriggerPending | NotifyType::Complete => {
+                Some(OrderAck::Accepted { basket_id })
+            }
+            _ => None,
+        }
+    }
+
+    /// Rejected when the exchange rejects the order, None for any other exchange notification
+    pub fn from_exchange_notification(notification: &ExchangeOrderNotification) -> Option<Self> {
+        use crate::rti::exchange_order_notification::NotifyType;
+
+        if notification.notify_type() != NotifyType::Reject {
+            return None;
+        }
+
+        let reason = notification
+            .text
+            .clone()
+            .or_else(|| notification.report_text.clone())
+            .unwrap_or_default();
+
+        Some(OrderAck::Rejected {
+            basket_id: notification.basket_id.clone(),
+            reason,
+        })
+    }
+}
+
+/// A fill reported by an exchange order notification, with both the size of this fill and
+/// the running totals of the order so each partial fill can be handled on its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct RithmicFill {
+    pub basket_id: String,
+    pub symbol: String,
+    pub exchange: String,
+    pub fill_id: Option<String>,
+    pub fill_price: f64,
+    pub fill_size: i32,
+    pub transaction_type: Option<TransactionType>,
+    pub avg_fill_price: Option<f64>,
+    pub total_fill_size: i32,
+    /// None when the notification reports neither the unfilled size nor the order quantity
+    pub total_unfilled_size: Option<i32>,
+}
+
+impl RithmicFill {
+    /// Returns None unless the notification reports a fill
+    pub fn from_notification(notification: &ExchangeOrderNotification) -> Option<Self> {
+        if notification.report_type.as_deref() != Some("fill") {
+            return None;
+        }
+
+        let fill_size = notification.fill_size?;
+        let total_fill_size = notification.total_fill_size.unwrap_or(fill_size);
+
+        Some(RithmicFill {
+            basket_id: notification.basket_id.clone()?,
+            symbol: notification.symbol.clone().unwrap_or_default(),
+            exchange: notification.exchange.clone().unwrap_or_default(),
+            fill_id: notification.fill_id.clone(),
+            fill_price: notification.fill_price?,
+            fill_size,
+            transaction_type: notification
+                .transaction_type
+                .and_then(|t| TransactionType::try_from(t).ok()),
+            avg_fill_price: notification.avg_fill_price,
+            total_fill_size,
+            total_unfilled_size: total_unfilled_size(notification),
+        })
+    }
+
+    /// Some of the order is still unfilled, None when the unfilled size is unknown
+    pub fn is_partial(&self) -> Option<bool> {
+        self.total_unfilled_size.map(|unfilled| unfilled > 0)
+    }
+}
+
+/// Size of the order left to fill after a fill notification. `total_unfilled_size` is optional,
+/// it is derived from the order quantity when missing. None when neither is sent.
+pub fn total_unfilled_size(notification: &ExchangeOrderNotification) -> Option<i32> {
+    let total_fill_size = notification.total_fill_size.or(notification.fill_size)?;
+
+    notification
+        .total_unfilled_size
+        .or_else(|| notification.quantity.map(|qty| qty - total_fill_size))
+}
+
+/// An order replayed by the order history request, its notifications folded into its final state
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RithmicHistoricalOrder {
+    pub basket_id: String,
+    pub symbol: String,
+    pub exchange: String,
+    pub quantity: i32,
+    pub price: Option<f64>,
+    pub status: Option<String>,
+    pub completion_reason: Option<String>,
+    pub fills: Vec<RithmicFill>,
+    /// Seconds since epoch of the first and last notification of the order
+    pub first_update_ssboe: Option<i32>,
+    pub last_update_ssboe: Option<i32>,
+}
+
+impl RithmicHistoricalOrder {
+    fn touch(&mut self, ssboe: Option<i32>) {
+        if self.first_update_ssboe.is_none() {
+            self.first_update_ssboe = ssboe;
+        }
+
+        if ssboe.is_some() {
+            self.last_update_ssboe = ssboe;
+        }
+    }
+
+    pub fn apply_order_notification(&mut self, notification: &RithmicOrderNotification) {
+        if let Some(symbol) = &notification.symbol {
+            self.symbol = symbol.clone();
+        }
+
+        if let Some(exchange) = &notification.exchange {
+            self.exchange = exchange.clone();
+        }
+
+        if let Some(quantity) = notification.quantity {
+            self.quantity = quantity;
+        }
+
+        if notification.price.is_some() {
+            self.price = notification.price;
+        }
+
+        if notification.status.is_some() {
+            self.status = notification.status.clone();
+        }
+
+        if notification.completion_reason.is_some() {
+            self.completion_reason = notification.completion_reason.clone();
+        }
+
+        self.touch(notification.ssboe);
+    }
+
+    pub fn apply_exchange_notification(&mut self, notification: &ExchangeOrderNotification) {
+        if let Some(fill) = RithmicFill::from_notification(notification) {
+            self.fills.push(fill);
+        }
+
+        self.touch(notification.ssboe);
+    }
+}
+
+/// Net position of an instrument rebuilt from its executions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RithmicNetPosition {
+    pub symbol: String,
+    pub exchange: String,
+    pub buy_quantity: i32,
+    pub sell_quantity: i32,
+}
+
+impl RithmicNetPosition {
+    pub fn apply_fill(&mut self, fill: &RithmicFill) {
+        match fill.transaction_type {
+            Some(TransactionType::Buy) => self.buy_quantity += fill.fill_size,
+            Some(TransactionType::Sell) | Some(TransactionType::Ss) => {
+                self.sell_quantity += fill.fill_size
+            }
+            None => {}
+        }
+    }
+
+    /// Positive when long, negative when short
+    pub fn net_quantity(&self) -> i32 {
+        self.buy_quantity - self.sell_quantity
+    }
+}
+
+/// Auto liquidation state of an account, sent by the order plant when the threshold moves.
+/// Once the account balance falls to `threshold` the broker flattens the account.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RithmicAutoLiquidation {
+    pub account_id: String,
+    pub threshold: Option<f64>,
+    pub peak_account_balance: Option<f64>,
+    pub peak_account_balance_ssboe: Option<i32>,
+}
+
+impl RithmicAutoLiquidation {
+    pub fn from_update(update: &AccountRmsUpdates) -> Self {
+        RithmicAutoLiquidation {
+            account_id: update.account_id.clone().unwrap_or_default(),
+            threshold: parse(&update.auto_liq_threshold_current_value),
+            peak_account_balance: parse(&update.auto_liq_peak_account_balance),
+            peak_account_balance_ssboe: parse(&update.auto_liq_peak_account_balance_ssboe),
+        }
+    }
+}
+
+/// Checks the size of the tick bars to replay: trades per bar for tick bars, contracts for
+/// volume bars and ticks of range for range bars, e.g. "500" for 500 contracts volume bars
+pub fn validate_bar_type_specifier(
+    bar_type: request_tick_bar_replay::BarType,
+    bar_type_specifier: &str,
+) -> Result<(), String> {
+    match bar_type_specifier.trim().parse::<u32>() {
+        Ok(size) if size > 0 => Ok(()),
+        _ => Err(format!(
+            "{:?} requires a positive bar size as bar_type_specifier, got {:?}",
+            bar_type, bar_type_specifier
+        )),
+    }
+}
+
+/// Numeric fields of the rms messages are sent as strings
+fn parse<T: std::str::FromStr>(value: &Option<String>) -> Option<T> {
+    value.as_deref().and_then(|v| v.trim().parse().ok())
+}
+
+/// One side of the top of book
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RithmicQuote {
+    pub price: f64,
+    /// Total size, implied included
+    pub size: Option<i32>,
+    pub orders: Option<i32>,
+    /// Part of `size` coming from implied prices, e.g. spreads legged in by the exchange
+    pub implicit_size: Option<i32>,
+}
+
+impl RithmicQuote {
+    /// Size of the outright orders resting at the price, `size` without the implied part
+    pub fn actual_size(&self) -> Option<i32> {
+        self.size.map(|size| size - self.implicit_size.unwrap_or(0))
+    }
+}
+
+/// Best bid and offer of an instrument, rebuilt from its `BestBidOffer` updates. A side is None
+/// until it's received and after the exchange clears it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RithmicBbo {
+    pub bid: Option<RithmicQuote>,
+    pub ask: Option<RithmicQuote>,
+    pub ssboe: Option<i32>,
+    pub usecs: Option<i32>,
+}
+
+impl RithmicBbo {
+    /// Updates only carry the sides flagged in their presence bits, the other side is kept
+    pub fn apply(&mut self, update: &BestBidOffer) {
+        let presence_bits = update.presence_bits.unwrap_or(0);
+        let clear_bits = update.clear_bits.unwrap_or(0);
+
+        if clear_bits & PresenceBits::Bid as u32 != 0 {
+            self.bid = None;
+        }
+
+        if clear_bits & PresenceBits::Ask as u32 != 0 {
+            self.ask = None;
+        }
+
+        if presence_bits & PresenceBits::Bid as u32 != 0
+            && let Some(price) = update.bid_price
+        {
+            self.bid = Some(RithmicQuote {
+                price,
+                size: update.bid_size,
+                orders: update.bid_orders,
+                implicit_size: update.bid_implicit_size,
+            });
+        }
+
+        if presence_bits & PresenceBits::Ask as u32 != 0
+            && let Some(price) = update.ask_price
+        {
+            self.ask = Some(RithmicQuote {
+                price,
+                size: update.ask_size,
+                orders: update.ask_orders,
+                implicit_size: update.ask_implicit_size,
+            });
+        }
+
+        self.ssboe = update.ssboe.or(self.ssboe);
+        self.usecs = update.usecs.or(self.usecs);
+    }
+}
+
+/// Set of market data fields to subscribe to, combined with `|`, e.g.
+/// `UpdateBits::LastTrade | UpdateBits::Bbo`. Converts into the `Vec<UpdateBits>` taken by the
+/// ticker plant subscribe methods.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MarketDataFieldSet(i32);
+
+impl MarketDataFieldSet {
+    const FIELDS: [UpdateBits; 17] = [
+        UpdateBits::LastTrade,
+        UpdateBits::Bbo,
+        UpdateBits::OrderBook,
+        UpdateBits::Open,
+        UpdateBits::OpeningIndicator,
+        UpdateBits::HighLow,
+        UpdateBits::HighBidLowAsk,
+        UpdateBits::Close,
+        UpdateBits::ClosingIndicator,
+        UpdateBits::Settlement,
+        UpdateBits::MarketMode,
+        UpdateBits::OpenInterest,
+        UpdateBits::MarginRate,
+        UpdateBits::HighPriceLimit,
+        UpdateBits::LowPriceLimit,
+        UpdateBits::ProjectedSettlement,
+        UpdateBits::AdjustedClose,
+    ];
+
+    pub fn empty() -> Self {
+        MarketDataFieldSet(0)
+    }
+
+    pub fn all() -> Self {
+        Self::FIELDS.into_iter().fold(Self::empty(), |set, field| set | field)
+    }
+
+    /// Last trade and best bid/offer
+    pub fn quotes() -> Self {
+        UpdateBits::LastTrade | UpdateBits::Bbo
+    }
+
+    /// Quotes and the order book
+    pub fn full_depth() -> Self {
+        Self::quotes() | UpdateBits::OrderBook
+    }
+
+    pub fn contains(&self, field: UpdateBits) -> bool {
+        self.0 & field as i32 != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn fields(&self) -> Vec<UpdateBits> {
+        Self::FIELDS
+            .into_iter()
+            .filter(|field| self.contains(*field))
+            .collect()
+    }
+}
+
+impl From<UpdateBits> for MarketDataFieldSet {
+    fn from(field: UpdateBits) -> Self {
+        MarketDataFieldSet(field as i32)
+    }
+}
+
+impl From<MarketDataFieldSet> for Vec<UpdateBits> {
+    fn from(set: MarketDataFieldSet) -> Self {
+        set.fields()
+    }
+}
+
+impl BitOr for MarketDataFieldSet {
+    type Output = MarketDataFieldSet;
+
+    fn bitor(self, rhs: MarketDataFieldSet) -> MarketDataFieldSet {
+        MarketDataFieldSet(self.0 | rhs.0)
+    }
+}
+
+impl BitOr<UpdateBits> for MarketDataFieldSet {
+    type Output = MarketDataFieldSet;
+
+    fn bitor(self, rhs: UpdateBits) -> MarketDataFieldSet {
+        self | MarketDataFieldSet::from(rhs)
+    }
+}
+
+impl BitOr for UpdateBits {
+    type Output = MarketDataFieldSet;
+
+    fn bitor(self, rhs: UpdateBits) -> MarketDataFieldSet {
+        MarketDataFieldSet::from(self) | rhs
+    }
+}
+
+/// Expiration date of a contract, from the `YYYYMMDD` expiration_date of its reference data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExpirationDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl ExpirationDate {
+    pub fn parse(date: &str) -> Option<Self> {
+        let date = date.trim();
+
+        if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let expiration = ExpirationDate {
+            year: date[..4].parse().ok()?,
+            month: date[4..6].parse().ok()?,
+            day: date[6..].parse().ok()?,
+        };
+
+        ((1..=12).contains(&expiration.month) && (1..=31).contains(&expiration.day))
+            .then_some(expiration)
+    }
+
+    pub fn from_reference_data(reference_data: &ResponseReferenceData) -> Option<Self> {
+        Self::parse(reference_data.expiration_date.as_deref()?)
+    }
+
+    /// Days since 1970-01-01, negative before
+    pub fn days_since_epoch(&self) -> i64 {
+        // Days from civil, see http://howardhinnant.github.io/date_algorithms.html
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146097 + day_of_era - 719468
+    }
+
+    /// Whole days from the UTC date of `now` to the expiration, 0 on the expiration day and
+    /// negative once expired
+    pub fn days_from(&self, now: SystemTime) -> i64 {
+        let today = match now.duration_since(UNIX_EPOCH) {
+            Ok(since_epoch) => (since_epoch.as_secs() / 86_400) as i64,
+            Err(before_epoch) => -(before_epoch.duration().as_secs().div_ceil(86_400) as i64),
+        };
+
+        self.days_since_epoch() - today
+    }
+}
+
+/// Pre-flight checks of an instrument before placing an order, see `ticker_plant::can_trade`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TradeabilityReport {
+    pub entitled: bool,
+    /// None until a market mode update is received for the instrument
+    pub market_open: Option<bool>,
+    /// None until a market mode update is received for the instrument
+    pub not_halted: Option<bool>,
+    pub reference_data_known: bool,
+    /// Why each failed check failed
+    pub reasons: Vec<String>,
+}
+
+impl TradeabilityReport {
+    /// Whether no check failed. An unknown market mode doesn't fail the check.
+    pub fn can_trade(&self) -> bool {
+        self.entitled
+            && self.reference_data_known
+            && self.market_open != Some(false)
+            && self.not_halted != Some(false)
+    }
 }
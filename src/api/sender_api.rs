@@ -2,12 +2,16 @@ use bytes::Bytes;
 use prost::Message;
 
 use crate::{
-    api::RithmicConnectionInfo,
+    api::{RithmicConnectionInfo, TEMPLATE_VERSION},
     rti::{*,
         request_login::SysInfraType,
     },
 };
-use super::rithmic_command_types::RithmicBracketOrder;
+use super::{
+    frame_capture::{FrameCapture, FrameDirection},
+    rithmic_command_types::{OrderPlacement, RithmicBracketOrder, RithmicModifyOrder, RithmicOrder},
+    templates::*,
+};
 
 pub const TRADE_ROUTE_LIVE: &str = "globex";
 pub const TRADE_ROUTE_DEMO: &str = "simulator";
@@ -18,8 +22,10 @@ pub struct RithmicSenderApi {
     account_id: String,
     conn_info: RithmicConnectionInfo,
     fcm_id: String,
+    frame_capture: Option<FrameCapture>,
     ib_id: String,
     message_id_counter: u64,
+    order_placement: OrderPlacement,
 }
 
 impl RithmicSenderApi {
@@ -28,8 +34,39 @@ impl RithmicSenderApi {
             account_id: "".to_string(),
             conn_info: conn_info.clone(),
             fcm_id: "".to_string(),
+            frame_capture: None,
             ib_id: "".to_string(),
             message_id_counter: 0,
+            order_placement: OrderPlacement::default(),
+        }
+    }
+
+    pub fn set_frame_capture(&mut self, frame_capture: Option<FrameCapture>) {
+        self.frame_capture = frame_capture;
+    }
+
+    /// Default tagging of orders, modifies and cancels that don't set their own `auto` flag
+    pub fn set_order_placement(&mut self, order_placement: OrderPlacement) {
+        self.order_placement = order_placement;
+    }
+
+    fn manual_or_auto(&self, auto: Option<bool>) -> i32 {
+        self.order_placement.resolve(auto).into()
+    }
+
+    pub fn set_account(&mut self, fcm_id: &str, ib_id: &str, account_id: &str) {
+        self.fcm_id = fcm_id.to_string();
+        self.ib_id = ib_id.to_string();
+        self.account_id = account_id.to_string();
+    }
+
+    /// Account scoped requests sent with an empty account_id are rejected by the
+    /// server with an unhelpful message, so callers check this before encoding.
+    pub fn require_account(&self) -> Result<(), String> {
+        if self.account_id.is_empty() {
+            Err("no active account: account_id is empty, call select_account first".to_string())
+        } else {
+            Ok(())
         }
     }
 
@@ -46,14 +83,20 @@ impl RithmicSenderApi {
         buf.extend_from_slice(&header); // Ajout du header
         req.encode(&mut buf).unwrap(); // Encodage du message
 
-        (Bytes::from(buf), id)
+        let buf = Bytes::from(buf);
+
+        if let Some(frame_capture) = &self.frame_capture {
+            frame_capture.record(FrameDirection::Outbound, &buf);
+        }
+
+        (buf, id)
     }
 
     pub fn request_get_instrument_by_underlying(&mut self) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestGetInstrumentByUnderlying {
-            template_id: 103,
+            template_id: REQUEST_GET_INSTRUMENT_BY_UNDERLYING,
             ..RequestGetInstrumentByUnderlying::default()
         };
 
@@ -64,7 +107,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestHeartbeat {
-            template_id: 18,
+            template_id: REQUEST_HEARTBEAT,
             user_msg: vec![id.clone()],
             ..RequestHeartbeat::default()
         };
@@ -82,8 +125,13 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestLogin {
-            template_id: 10,
-            template_version: Some("5.27".into()),
+            template_id: REQUEST_LOGIN,
+            template_version: Some(
+                self.conn_info
+                    .template_version
+                    .clone()
+                    .unwrap_or_else(|| TEMPLATE_VERSION.to_string()),
+            ),
             user: Some(user.to_string()),
             password: Some(password.to_string()),
             app_name: Some("pede:pts".to_string()),
@@ -101,7 +149,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestLogout {
-            template_id: 12,
+            template_id: REQUEST_LOGOUT,
             user_msg: vec![id.clone()],
         };
 
@@ -118,7 +166,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let mut req = RequestMarketDataUpdate {
-            template_id: 100,
+            template_id: REQUEST_MARKET_DATA_UPDATE,
             user_msg: vec![id.clone()],
             ..RequestMarketDataUpdate::default()
         };
@@ -141,7 +189,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestProductCodes {
-            template_id: 111,
+            template_id: REQUEST_PRODUCT_CODES,
             user_msg: vec![id.clone()],
             exchange,
             give_toi_products_only: Some(true),
@@ -149,11 +197,30 @@ impl RithmicSenderApi {
 
         self.request_to_buf(req, id)
     }
+    pub fn request_front_month_contract(
+        &mut self,
+        symbol: &str,
+        exchange: &str,
+        need_updates: bool,
+    ) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestFrontMonthContract {
+            template_id: REQUEST_FRONT_MONTH_CONTRACT,
+            user_msg: vec![id.clone()],
+            symbol: Some(symbol.to_string()),
+            exchange: Some(exchange.to_string()),
+            need_updates: Some(need_updates),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
     pub fn request_reference_data(&mut self, symbol: Option<String>, exchange: Option<String>) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestReferenceData {
-            template_id: 14,
+            template_id: REQUEST_REFERENCE_DATA,
             user_msg: vec![id.clone()],
             symbol,
             exchange,
@@ -166,7 +233,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestRithmicSystemGatewayInfo {
-            template_id: 20,
+            template_id: REQUEST_RITHMIC_SYSTEM_GATEWAY_INFO,
             user_msg: vec![id.clone()],
             system_name: Some(system_name),
         };
@@ -178,7 +245,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestRithmicSystemInfo {
-            template_id: 16,
+            template_id: REQUEST_RITHMIC_SYSTEM_INFO,
             user_msg: vec![id.clone()],
         };
 
@@ -193,7 +260,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestSearchSymbols {
-            template_id: 109,
+            template_id: REQUEST_SEARCH_SYMBOLS,
             user_msg: vec![id.clone()],
             search_text,
             instrument_type: if instrument_type.is_some() {Some(instrument_type.unwrap() as i32)} else {None},
@@ -206,11 +273,25 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
+    pub fn request_account_list(&mut self, fcm_id: &str, ib_id: &str) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestAccountList {
+            template_id: REQUEST_ACCOUNT_LIST,
+            user_msg: vec![id.clone()],
+            fcm_id: Some(fcm_id.into()),
+            ib_id: Some(ib_id.into()),
+            user_type: Some(USER_TYPE),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
     pub fn request_subscribe_for_order_updates(&mut self) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestSubscribeForOrderUpdates {
-            template_id: 308,
+            template_id: REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -224,7 +305,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestSubscribeToBracketUpdates {
-            template_id: 336,
+            template_id: REQUEST_SUBSCRIBE_TO_BRACKET_UPDATES,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -249,7 +330,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestTickBarReplay {
-            template_id: 206,
+            template_id: REQUEST_TICK_BAR_REPLAY,
             user_msg: vec![id.clone()],
             symbol: Some(symbol.into()),
             exchange: Some(exchange.into()),
@@ -278,7 +359,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestTickBarUpdate {
-            template_id: 204,
+            template_id: REQUEST_TICK_BAR_UPDATE,
             user_msg: vec![id.clone()],
             symbol: Some(symbol.into()),
             exchange: Some(exchange.into()),
@@ -306,7 +387,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestTimeBarReplay {
-            template_id: 202,
+            template_id: REQUEST_TIME_BAR_REPLAY,
             user_msg: vec![id.clone()],
             symbol: Some(symbol.into()),
             exchange: Some(exchange.into()),
@@ -322,6 +403,20 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
+    /// Resume a bar replay the server stopped at its bar limit, `request_key` as carried by
+    /// the last response of the replay
+    pub fn request_resume_bars(&mut self, request_key: &str) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestResumeBars {
+            template_id: REQUEST_RESUME_BARS,
+            user_msg: vec![id.clone()],
+            request_key: Some(request_key.into()),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
     pub fn request_time_bar_update(
         &mut self,
         symbol: &str,
@@ -333,7 +428,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestTimeBarUpdate {
-            template_id: 200,
+            template_id: REQUEST_TIME_BAR_UPDATE,
             user_msg: vec![id.clone()],
             symbol: Some(symbol.into()),
             exchange: Some(exchange.into()),
@@ -346,44 +441,37 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
-    pub fn request_new_order(
-        &mut self,
-        exchange: &str,
-        symbol: &str,
-        qty: i32,
-        price: f64,
-        action: request_new_order::TransactionType,
-        ordertype: request_new_order::PriceType,
-        localid: &str,
-
-        // optional args
-        duration: Option<request_new_order::Duration>,
-    ) -> (Bytes, String) {
+    pub fn request_new_order(&mut self, order: &RithmicOrder) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         // TODO
         let trade_route = "";
 
+        // An order with a cancel time is a GTC order that the server cancels at that time
+        let default_duration = if order.cancel_at_ssboe.is_some() {
+            request_new_order::Duration::Gtc
+        } else {
+            request_new_order::Duration::Day
+        };
+
         let req = RequestNewOrder {
-            template_id: 312,
+            template_id: REQUEST_NEW_ORDER,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
-            account_id: Some(self.account_id.clone()),
+            account_id: Some(order.account_id.clone().unwrap_or_else(|| self.account_id.clone())),
             trade_route: Some(trade_route.into()),
-            exchange: Some(exchange.into()),
-            symbol: Some(symbol.into()),
-            quantity: Some(qty),
-            price: Some(price),
-            transaction_type: Some(action.into()),
-            price_type: Some(ordertype.into()),
-            manual_or_auto: Some(2),
-            duration: if let Some(d) = duration {
-                Some(d.into())
-            } else {
-                Some(1)
-            },
-            user_msg: vec![id.clone()],
-            user_tag: Some(localid.into()),
+            exchange: Some(order.exchange.clone()),
+            symbol: Some(order.symbol.clone()),
+            quantity: Some(order.qty),
+            price: Some(order.price),
+            trigger_price: order.trigger_price,
+            transaction_type: Some(order.action.into()),
+            price_type: Some(order.ordertype.into()),
+            manual_or_auto: Some(self.manual_or_auto(order.auto)),
+            duration: Some(order.duration.unwrap_or(default_duration).into()),
+            cancel_at_ssboe: order.cancel_at_ssboe,
+            user_msg: std::iter::once(id.clone()).chain(order.correlation.clone()).collect(),
+            user_tag: Some(order.localid.clone()),
             ..RequestNewOrder::default()
         };
 
@@ -400,7 +488,7 @@ impl RithmicSenderApi {
         let trade_route = "";
 
         let req = RequestBracketOrder {
-            template_id: 330,
+            template_id: REQUEST_BRACKET_ORDER,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -411,7 +499,7 @@ impl RithmicSenderApi {
             quantity: Some(bracket_order.qty),
             transaction_type: Some(bracket_order.action),
             price_type: Some(bracket_order.ordertype),
-            manual_or_auto: Some(2),
+            manual_or_auto: Some(self.manual_or_auto(bracket_order.auto)),
             duration: Some(bracket_order.duration),
             bracket_type: Some(6),
             target_quantity: Some(bracket_order.qty),
@@ -423,6 +511,7 @@ impl RithmicSenderApi {
             } else {
                 None
             },
+            trigger_price: bracket_order.trigger_price,
             user_msg: vec![id.clone()],
             user_tag: Some(bracket_order.localid),
             ..RequestBracketOrder::default()
@@ -431,50 +520,39 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
-    pub fn request_modify_order(
-        &mut self,
-        basket_id: &str,
-        exchange: &str,
-        symbol: &str,
-        qty: i32,
-        price: f64,
-        ordertype: i32,
-    ) -> (Bytes, String) {
+    pub fn request_modify_order(&mut self, order: &RithmicModifyOrder) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestModifyOrder {
-            template_id: 314,
+            template_id: REQUEST_MODIFY_ORDER,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
-            basket_id: Some(basket_id.into()),
-            manual_or_auto: Some(2),
-            exchange: Some(exchange.into()),
-            symbol: Some(symbol.into()),
-            price_type: Some(ordertype),
-            quantity: Some(qty),
-            price: Some(price),
+            basket_id: Some(order.id.clone()),
+            manual_or_auto: Some(self.manual_or_auto(order.auto)),
+            exchange: Some(order.exchange.clone()),
+            symbol: Some(order.symbol.clone()),
+            price_type: Some(order.ordertype),
+            quantity: Some(order.qty),
+            price: order.price,
             user_msg: vec![id.clone()],
-            trigger_price: match ordertype {
-                3 | 4 => Some(price),
-                _ => None,
-            },
+            trigger_price: order.trigger_price,
             ..RequestModifyOrder::default()
         };
 
         self.request_to_buf(req, id)
     }
 
-    pub fn request_cancel_order(&mut self, basket_id: &str) -> (Bytes, String) {
+    pub fn request_cancel_order(&mut self, basket_id: &str, auto: Option<bool>) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestCancelOrder {
-            template_id: 316,
+            template_id: REQUEST_CANCEL_ORDER,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
             basket_id: Some(basket_id.into()),
-            manual_or_auto: Some(2),
+            manual_or_auto: Some(self.manual_or_auto(auto)),
             user_msg: vec![id.clone()],
             ..RequestCancelOrder::default()
         };
@@ -482,17 +560,57 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
+    pub fn request_list_exchange_permissions(&mut self) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestListExchangePermissions {
+            template_id: REQUEST_LIST_EXCHANGE_PERMISSIONS,
+            user_msg: vec![id.clone()],
+            user: Some(self.conn_info.user.clone()),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    pub fn request_trade_routes(&mut self) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestTradeRoutes {
+            template_id: REQUEST_TRADE_ROUTES,
+            user_msg: vec![id.clone()],
+            subscribe_for_updates: Some(false),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    pub fn request_cancel_all_orders(&mut self) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestCancelAllOrders {
+            template_id: REQUEST_CANCEL_ALL_ORDERS,
+            fcm_id: Some(self.fcm_id.clone()),
+            ib_id: Some(self.ib_id.clone()),
+            account_id: Some(self.account_id.clone()),
+            user_type: Some(USER_TYPE),
+            manual_or_auto: Some(self.manual_or_auto(None)),
+            user_msg: vec![id.clone()],
+        };
+
+        self.request_to_buf(req, id)
+    }
+
     pub fn request_exit_position(&mut self, symbol: &str, exchange: &str) -> (Bytes, String) {
         let id = self.get_next_message_id();
 
         let req = RequestExitPosition {
-            template_id: 3504,
+            template_id: REQUEST_EXIT_POSITION,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
             symbol: Some(symbol.into()),
             exchange: Some(exchange.into()),
-            manual_or_auto: Some(2),
+            manual_or_auto: Some(self.manual_or_auto(None)),
             user_msg: vec![id.clone()],
             ..RequestExitPosition::default()
         };
@@ -500,6 +618,38 @@ impl RithmicSenderApi {
         self.request_to_buf(req, id)
     }
 
+    pub fn request_replay_executions(&mut self, start_index: i32, finish_index: i32) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestReplayExecutions {
+            template_id: REQUEST_REPLAY_EXECUTIONS,
+            user_msg: vec![id.clone()],
+            fcm_id: Some(self.fcm_id.clone()),
+            ib_id: Some(self.ib_id.clone()),
+            account_id: Some(self.account_id.clone()),
+            start_index: Some(start_index),
+            finish_index: Some(finish_index),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    pub fn request_account_rms_updates(&mut self) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestAccountRmsUpdates {
+            template_id: REQUEST_ACCOUNT_RMS_UPDATES,
+            user_msg: vec![id.clone()],
+            fcm_id: Some(self.fcm_id.clone()),
+            ib_id: Some(self.ib_id.clone()),
+            account_id: Some(self.account_id.clone()),
+            request: Some("subscribe".to_string()),
+            update_bits: Some(request_account_rms_updates::UpdateBits::AutoLiqThresholdCurrentValue.into()),
+        };
+
+        self.request_to_buf(req, id)
+    }
+
     pub fn request_update_target_bracket_level(
         &mut self,
         basket_id: &str,
@@ -508,7 +658,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestUpdateTargetBracketLevel {
-            template_id: 332,
+            template_id: REQUEST_UPDATE_TARGET_BRACKET_LEVEL,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -529,7 +679,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestUpdateStopBracketLevel {
-            template_id: 334,
+            template_id: REQUEST_UPDATE_STOP_BRACKET_LEVEL,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -546,7 +696,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestShowBrackets {
-            template_id: 338,
+            template_id: REQUEST_SHOW_BRACKETS,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -560,7 +710,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestShowBracketStops {
-            template_id: 340,
+            template_id: REQUEST_SHOW_BRACKET_STOPS,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -574,10 +724,52 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestShowOrders {
-            template_id: 320,
+            template_id: REQUEST_SHOW_ORDERS,
+            fcm_id: Some(self.fcm_id.clone()),
+            ib_id: Some(self.ib_id.clone()),
+            account_id: Some(self.account_id.clone()),
+            user_msg: vec![id.clone()],
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    pub fn request_show_order_history(&mut self, basket_id: Option<&str>) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestShowOrderHistory {
+            template_id: REQUEST_SHOW_ORDER_HISTORY,
+            fcm_id: Some(self.fcm_id.clone()),
+            ib_id: Some(self.ib_id.clone()),
+            account_id: Some(self.account_id.clone()),
+            basket_id: basket_id.map(|b| b.into()),
+            user_msg: vec![id.clone()],
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    pub fn request_show_order_history_dates(&mut self) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestShowOrderHistoryDates {
+            template_id: REQUEST_SHOW_ORDER_HISTORY_DATES,
+            user_msg: vec![id.clone()],
+        };
+
+        self.request_to_buf(req, id)
+    }
+
+    /// `date` as returned by `request_show_order_history_dates`, YYYYMMDD
+    pub fn request_show_order_history_summary(&mut self, date: &str) -> (Bytes, String) {
+        let id = self.get_next_message_id();
+
+        let req = RequestShowOrderHistorySummary {
+            template_id: REQUEST_SHOW_ORDER_HISTORY_SUMMARY,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
+            date: Some(date.into()),
             user_msg: vec![id.clone()],
         };
 
@@ -591,7 +783,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestPnLPositionUpdates {
-            template_id: 400,
+            template_id: REQUEST_PNL_POSITION_UPDATES,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
@@ -606,7 +798,7 @@ impl RithmicSenderApi {
         let id = self.get_next_message_id();
 
         let req = RequestPnLPositionSnapshot {
-            template_id: 402,
+            template_id: REQUEST_PNL_POSITION_SNAPSHOT,
             fcm_id: Some(self.fcm_id.clone()),
             ib_id: Some(self.ib_id.clone()),
             account_id: Some(self.account_id.clone()),
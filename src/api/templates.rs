@@ -0,0 +1,122 @@
+//! Template ids of the R | Protocol messages handled by this crate.
+//!
+//! Requests are sent by `RithmicSenderApi`, responses and updates are decoded
+//! by `RithmicReceiverApi`.
+
+/// Declare each template id constant once and the ascending list of them named `$list`, so
+/// the supported template lists can't drift from the constants.
+macro_rules! templates {
+    ($list:ident { $($name:ident = $id:literal,)* }) => {
+        $(pub const $name: i32 = $id;)*
+
+        pub(crate) static $list: &[u16] = &[$($id,)*];
+    };
+}
+
+templates!(REQUEST_TEMPLATES {
+    REQUEST_LOGIN = 10,
+    REQUEST_LOGOUT = 12,
+    REQUEST_REFERENCE_DATA = 14,
+    REQUEST_RITHMIC_SYSTEM_INFO = 16,
+    REQUEST_HEARTBEAT = 18,
+    REQUEST_RITHMIC_SYSTEM_GATEWAY_INFO = 20,
+    REQUEST_MARKET_DATA_UPDATE = 100,
+    REQUEST_GET_INSTRUMENT_BY_UNDERLYING = 102,
+    REQUEST_SEARCH_SYMBOLS = 109,
+    REQUEST_PRODUCT_CODES = 111,
+    REQUEST_FRONT_MONTH_CONTRACT = 113,
+    REQUEST_TIME_BAR_UPDATE = 200,
+    REQUEST_TIME_BAR_REPLAY = 202,
+    REQUEST_TICK_BAR_UPDATE = 204,
+    REQUEST_TICK_BAR_REPLAY = 206,
+    REQUEST_RESUME_BARS = 210,
+    REQUEST_ACCOUNT_LIST = 302,
+    REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES = 308,
+    REQUEST_TRADE_ROUTES = 310,
+    REQUEST_NEW_ORDER = 312,
+    REQUEST_MODIFY_ORDER = 314,
+    REQUEST_CANCEL_ORDER = 316,
+    REQUEST_SHOW_ORDER_HISTORY_DATES = 318,
+    REQUEST_SHOW_ORDERS = 320,
+    REQUEST_SHOW_ORDER_HISTORY = 322,
+    REQUEST_SHOW_ORDER_HISTORY_SUMMARY = 324,
+    REQUEST_BRACKET_ORDER = 330,
+    REQUEST_UPDATE_TARGET_BRACKET_LEVEL = 332,
+    REQUEST_UPDATE_STOP_BRACKET_LEVEL = 334,
+    REQUEST_SUBSCRIBE_TO_BRACKET_UPDATES = 336,
+    REQUEST_SHOW_BRACKETS = 338,
+    REQUEST_SHOW_BRACKET_STOPS = 340,
+    REQUEST_LIST_EXCHANGE_PERMISSIONS = 342,
+    REQUEST_CANCEL_ALL_ORDERS = 346,
+    REQUEST_PNL_POSITION_UPDATES = 400,
+    REQUEST_PNL_POSITION_SNAPSHOT = 402,
+    REQUEST_EXIT_POSITION = 3504,
+    REQUEST_REPLAY_EXECUTIONS = 3506,
+    REQUEST_ACCOUNT_RMS_UPDATES = 3508,
+});
+
+// Responses and updates
+templates!(RESPONSE_TEMPLATES {
+    RESPONSE_LOGIN = 11,
+    RESPONSE_LOGOUT = 13,
+    RESPONSE_REFERENCE_DATA = 15,
+    RESPONSE_RITHMIC_SYSTEM_INFO = 17,
+    RESPONSE_HEARTBEAT = 19,
+    RESPONSE_RITHMIC_SYSTEM_GATEWAY_INFO = 21,
+    REJECT = 75,
+    FORCED_LOGOUT = 77,
+    RESPONSE_MARKET_DATA_UPDATE = 101,
+    RESPONSE_GET_INSTRUMENT_BY_UNDERLYING = 103,
+    RESPONSE_SEARCH_SYMBOLS = 110,
+    RESPONSE_PRODUCT_CODES = 112,
+    RESPONSE_FRONT_MONTH_CONTRACT = 114,
+    LAST_TRADE = 150,
+    BEST_BID_OFFER = 151,
+    END_OF_DAY_PRICES = 155,
+    ORDER_BOOK = 156,
+    MARKET_MODE = 157,
+    OPEN_INTEREST = 158,
+    FRONT_MONTH_CONTRACT_UPDATE = 159,
+    DEPTH_BY_ORDER = 160,
+    SYMBOL_MARGIN_RATE = 162,
+    RESPONSE_TIME_BAR_UPDATE = 201,
+    RESPONSE_TIME_BAR_REPLAY = 203,
+    RESPONSE_TICK_BAR_UPDATE = 205,
+    RESPONSE_TICK_BAR_REPLAY = 207,
+    RESPONSE_VOLUME_PROFILE_MINUTE_BARS = 209,
+    RESPONSE_RESUME_BARS = 211,
+    TIME_BAR = 250,
+    TICK_BAR = 251,
+    RESPONSE_ACCOUNT_LIST = 303,
+    RESPONSE_ACCOUNT_RMS_INFO = 305,
+    RESPONSE_PRODUCT_RMS_INFO = 307,
+    RESPONSE_SUBSCRIBE_FOR_ORDER_UPDATES = 309,
+    RESPONSE_TRADE_ROUTES = 311,
+    RESPONSE_NEW_ORDER = 313,
+    RESPONSE_MODIFY_ORDER = 315,
+    RESPONSE_CANCEL_ORDER = 317,
+    RESPONSE_SHOW_ORDER_HISTORY_DATES = 319,
+    RESPONSE_SHOW_ORDERS = 321,
+    RESPONSE_SHOW_ORDER_HISTORY = 323,
+    RESPONSE_SHOW_ORDER_HISTORY_SUMMARY = 325,
+    RESPONSE_SHOW_ORDER_HISTORY_DETAIL = 327,
+    RESPONSE_BRACKET_ORDER = 331,
+    RESPONSE_UPDATE_TARGET_BRACKET_LEVEL = 333,
+    RESPONSE_UPDATE_STOP_BRACKET_LEVEL = 335,
+    RESPONSE_SUBSCRIBE_TO_BRACKET_UPDATES = 337,
+    RESPONSE_SHOW_BRACKETS = 339,
+    RESPONSE_SHOW_BRACKET_STOPS = 341,
+    RESPONSE_LIST_EXCHANGE_PERMISSIONS = 343,
+    RESPONSE_CANCEL_ALL_ORDERS = 347,
+    RITHMIC_ORDER_NOTIFICATION = 351,
+    EXCHANGE_ORDER_NOTIFICATION = 352,
+    BRACKET_UPDATES = 353,
+    ACCOUNT_RMS_UPDATES = 356,
+    RESPONSE_PNL_POSITION_UPDATES = 401,
+    RESPONSE_PNL_POSITION_SNAPSHOT = 403,
+    INSTRUMENT_PNL_POSITION_UPDATE = 450,
+    ACCOUNT_PNL_POSITION_UPDATE = 451,
+    RESPONSE_EXIT_POSITION = 3505,
+    RESPONSE_REPLAY_EXECUTIONS = 3507,
+    RESPONSE_ACCOUNT_RMS_UPDATES = 3509,
+});
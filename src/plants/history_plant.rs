@@ -1,23 +1,28 @@
+use std::{collections::HashSet, time::Duration};
+
 use async_trait::async_trait;
 use tracing::{event, Level};
 
 use crate::{
     api::{
-        RithmicConnectionInfo,
+        RithmicConnectionInfo, UnheardUpdates,
+        frame_capture::{CapturedFrame, FrameCapture},
         receiver_api::{RithmicReceiverApi, RithmicResponse},
+        rithmic_command_types::{RithmicTickBarRange, RithmicTimeBarRange, validate_bar_type_specifier},
         sender_api::RithmicSenderApi,
     },
     request_handler::{RithmicRequest, RithmicRequestHandler},
     rti::{
         *,
+        messages::RithmicMessage,
         request_login::SysInfraType,
     },
-    ws::{get_heartbeat_interval, PlantActor, RithmicStream, connect},
+    ws::{ConnectionMonitor, ConnectionStats, IdleTimer, UnsubscribeResponses, await_unsubscribes, get_heartbeat_interval, plant_not_connected, PlantActor, RithmicStream, connect_with_failover},
 };
 
 use futures_util::{
-    stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
+    stream::{self, SplitSink, SplitStream},
+    SinkExt, Stream, StreamExt,
 };
 
 use tokio_tungstenite::{
@@ -29,9 +34,13 @@ use tokio_tungstenite::{
 
 use tokio::{
     net::TcpStream,
-    sync::{broadcast::Sender, oneshot},
+    sync::{
+        broadcast::{error::RecvError, Sender},
+        oneshot,
+    },
     time::Interval,
 };
+use crate::plants::order_plant::CONNECTION_LOST;
 use crate::plants::ticker_plant::TickerPlantCommand;
 
 pub enum HistoryPlantCommand {
@@ -65,6 +74,10 @@ pub enum HistoryPlantCommand {
     Logout {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    ResumeBars {
+        request_key: String,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
     SendHeartbeat {},
     SetLogin,
     SubscribeTickBar {
@@ -84,32 +97,67 @@ pub enum HistoryPlantCommand {
         request_type: request_time_bar_update::Request,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    UnsubscribeAll {
+        response_sender: oneshot::Sender<UnsubscribeResponses>,
+    },
+}
+
+/// A live bar feed, unsubscribed on disconnect
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BarSubscription {
+    Tick {
+        symbol: String,
+        exchange: String,
+        bar_type: request_tick_bar_update::BarType,
+        bar_sub_type: request_tick_bar_update::BarSubType,
+        bar_type_specifier: String,
+    },
+    Time {
+        symbol: String,
+        exchange: String,
+        bar_type: request_time_bar_update::BarType,
+        bar_type_period: i32,
+    },
 }
 
 pub struct RithmicHistoryPlant {
     pub connection_handle: tokio::task::JoinHandle<()>,
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
     sender: tokio::sync::mpsc::Sender<HistoryPlantCommand>,
     subscription_sender: Sender<RithmicResponse>,
 }
 
 impl RithmicHistoryPlant {
+    /// Panics when the gateway can't be reached, see `try_new`
     pub async fn new(conn_info: &RithmicConnectionInfo) -> RithmicHistoryPlant {
+        Self::try_new(conn_info).await.unwrap()
+    }
+
+    /// Connect to the gateway, failing instead of panicking so an application can carry on
+    /// without this plant
+    pub async fn try_new(conn_info: &RithmicConnectionInfo) -> Result<RithmicHistoryPlant, String> {
         let (req_tx, req_rx) = tokio::sync::mpsc::channel::<HistoryPlantCommand>(32);
-        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(1024);
+        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(conn_info.update_buffer_size.max(1));
 
-        let mut history_plant = HistoryPlant::new(req_rx, sub_tx.clone(), conn_info)
-            .await
-            .unwrap();
+        let frame_capture = FrameCapture::from_capacity("history_plant", conn_info.capture_raw_frames);
+
+        let mut history_plant = HistoryPlant::new(req_rx, sub_tx.clone(), conn_info, frame_capture.clone())
+            .await?;
+
+        let connection_monitor = history_plant.connection_monitor.clone();
 
         let connection_handle = tokio::spawn(async move {
             history_plant.run().await;
         });
 
-        RithmicHistoryPlant {
+        Ok(RithmicHistoryPlant {
             connection_handle,
+            connection_monitor,
+            frame_capture,
             sender: req_tx,
             subscription_sender: sub_tx,
-        }
+        })
     }
 }
 
@@ -118,16 +166,22 @@ impl RithmicStream for RithmicHistoryPlant {
 
     fn get_handle(&self) -> RithmicHistoryPlantHandle {
         RithmicHistoryPlantHandle {
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
             sender: self.sender.clone(),
             subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
+            skipped_updates: 0,
         }
     }
 }
 
 #[derive(Debug)]
 pub struct HistoryPlant {
+    bar_subscriptions: HashSet<BarSubscription>,
     config: RithmicConnectionInfo,
+    connection_monitor: ConnectionMonitor,
+    idle_timer: IdleTimer,
     interval: Interval,
     logged_in: bool,
     request_handler: RithmicRequestHandler,
@@ -148,20 +202,33 @@ impl HistoryPlant {
         request_receiver: tokio::sync::mpsc::Receiver<HistoryPlantCommand>,
         subscription_sender: Sender<RithmicResponse>,
         conn_info: &RithmicConnectionInfo,
-    ) -> Result<HistoryPlant, ()> {
-        let config = conn_info.clone();
+        frame_capture: Option<FrameCapture>,
+    ) -> Result<HistoryPlant, String> {
+        let mut config = conn_info.clone();
 
-        let ws_stream = connect(&config.url).await.unwrap();
+        let (ws_stream, url) = connect_with_failover(&config.gateway_urls(), &config.ws_headers)
+            .await
+            .map_err(|e| format!("history_plant: {}", e))?;
+        // Reconnect to the gateway that worked first
+        config.url = url;
         let (rithmic_sender, rithmic_reader) = ws_stream.split();
-        let rithmic_sender_api = RithmicSenderApi::new(&config);
+        let mut rithmic_sender_api = RithmicSenderApi::new(&config);
+        rithmic_sender_api.set_frame_capture(frame_capture.clone());
+
         let rithmic_receiver_api = RithmicReceiverApi {
             source: "history_plant".to_string(),
+            frame_capture,
+            template_version: config.template_version.clone(),
         };
 
+        let idle_timer = IdleTimer::new(config.auto_logout_after_secs);
         let interval = get_heartbeat_interval();
 
         Ok(HistoryPlant {
+            bar_subscriptions: HashSet::new(),
             config,
+            connection_monitor: ConnectionMonitor::default(),
+            idle_timer,
             interval,
             logged_in: false,
             request_handler: RithmicRequestHandler::new(),
@@ -173,6 +240,39 @@ impl HistoryPlant {
             subscription_sender,
         })
     }
+
+    /// Send a frame to the gateway. A failed send is logged and fails the pending requests,
+    /// the actor keeps running so every handle gets an error rather than a stopped plant
+    async fn send_frame(&mut self, message: Message) {
+        if let Err(e) = self.rithmic_sender.send(message).await {
+            event!(Level::ERROR, "history_plant: send failed {}", e);
+
+            self.connection_monitor.error(&e);
+            self.request_handler.fail_pending(CONNECTION_LOST);
+        }
+    }
+
+    /// Log out and close the connection once no request has been sent for `auto_logout_after_secs`
+    async fn auto_logout(&mut self) {
+        event!(
+            Level::WARN,
+            "history_plant: no request for {}s, logging out",
+            self.config.auto_logout_after_secs
+        );
+
+        self.logged_in = false;
+
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        self.handle_command(HistoryPlantCommand::Logout { response_sender: tx }).await;
+        self.handle_command(HistoryPlantCommand::Close).await;
+
+        tokio::spawn(async move {
+            if let Ok(Err(e)) = rx.await {
+                event!(Level::ERROR, "history_plant: auto logout failed: {}", e);
+            }
+        });
+    }
 }
 
 #[async_trait]
@@ -187,11 +287,15 @@ impl PlantActor for HistoryPlant {
         loop {
             tokio::select! {
                 _ = self.interval.tick() => {
-                    if self.logged_in {
+                    if self.logged_in && self.idle_timer.expired() {
+                        self.auto_logout().await;
+                    } else if self.logged_in {
                         self.handle_command(HistoryPlantCommand::SendHeartbeat {}).await;
                     }
                 }
                 Some(message) = self.request_receiver.recv() => {
+                    self.idle_timer.touch();
+
                     self.handle_command(message).await;
                 }
                 Some(message) = self.rithmic_reader.next() => {
@@ -222,15 +326,22 @@ impl PlantActor for HistoryPlant {
 
                 stop = true;
             }
-            Ok(Message::Binary(data)) => {
-                let response = self.rithmic_receiver_api.buf_to_message(data).unwrap();
+            Ok(Message::Binary(data)) => match self.rithmic_receiver_api.buf_to_message(data) {
+                Ok(response) => {
+                    if self.request_handler.dispatch(response, &self.subscription_sender)
+                        && self.config.unheard_updates == UnheardUpdates::Close
+                    {
+                        event!(Level::WARN, "history_plant: no update receiver left, closing");
+
+                        self.handle_command(HistoryPlantCommand::Close).await;
+                    }
+                }
+                Err(e) => {
+                    event!(Level::ERROR, "history_plant: response from server: {:?}", e);
 
-                if response.is_update {
-                    self.subscription_sender.send(response).unwrap();
-                } else {
-                    self.request_handler.handle_response(response);
+                    self.connection_monitor.error(&e);
                 }
-            }
+            },
             Err(Error::ConnectionClosed) => {
                 event!(Level::INFO, "history_plant connection closed");
 
@@ -242,6 +353,10 @@ impl PlantActor for HistoryPlant {
                     "history_plant received unknown message {:?}",
                     message
                 );
+
+                if let Err(e) = &message {
+                    self.connection_monitor.error(e);
+                }
             }
         }
 
@@ -322,6 +437,19 @@ impl PlantActor for HistoryPlant {
                     .await
                     .unwrap();
             }
+            HistoryPlantCommand::ResumeBars {
+                request_key,
+                response_sender,
+            } => {
+                let (resume_buf, id) = self.rithmic_sender_api.request_resume_bars(&request_key);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(resume_buf)).await;
+            }
             HistoryPlantCommand::Login { response_sender } => {
                 let (login_buf, id) = self.rithmic_sender_api.request_login(
                     &self.config.system_name,
@@ -384,6 +512,20 @@ impl PlantActor for HistoryPlant {
                     request_type,
                 );
 
+                let subscription = BarSubscription::Tick {
+                    symbol,
+                    exchange,
+                    bar_type,
+                    bar_sub_type,
+                    bar_type_specifier,
+                };
+
+                if request_type == request_tick_bar_update::Request::Subscribe {
+                    self.bar_subscriptions.insert(subscription);
+                } else {
+                    self.bar_subscriptions.remove(&subscription);
+                }
+
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
@@ -410,6 +552,19 @@ impl PlantActor for HistoryPlant {
                     request_type,
                 );
 
+                let subscription = BarSubscription::Time {
+                    symbol,
+                    exchange,
+                    bar_type,
+                    bar_type_period,
+                };
+
+                if request_type == request_time_bar_update::Request::Subscribe {
+                    self.bar_subscriptions.insert(subscription);
+                } else {
+                    self.bar_subscriptions.remove(&subscription);
+                }
+
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
@@ -420,18 +575,107 @@ impl PlantActor for HistoryPlant {
                     .await
                     .unwrap();
             }
+            HistoryPlantCommand::UnsubscribeAll { response_sender } => {
+                let mut responses = Vec::new();
+
+                for subscription in self.bar_subscriptions.drain() {
+                    let (unsub_buf, id) = match subscription {
+                        BarSubscription::Tick {
+                            symbol,
+                            exchange,
+                            bar_type,
+                            bar_sub_type,
+                            bar_type_specifier,
+                        } => self.rithmic_sender_api.request_tick_bar_update(
+                            &symbol,
+                            &exchange,
+                            bar_type,
+                            bar_sub_type,
+                            &bar_type_specifier,
+                            request_tick_bar_update::Request::Unsubscribe,
+                        ),
+                        BarSubscription::Time {
+                            symbol,
+                            exchange,
+                            bar_type,
+                            bar_type_period,
+                        } => self.rithmic_sender_api.request_time_bar_update(
+                            &symbol,
+                            &exchange,
+                            bar_type,
+                            bar_type_period,
+                            request_time_bar_update::Request::Unsubscribe,
+                        ),
+                    };
+
+                    let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                    self.request_handler.register_request(RithmicRequest {
+                        request_id: id,
+                        responder: tx,
+                    });
+
+                    let _ = self.rithmic_sender.send(Message::Binary(unsub_buf)).await;
+
+                    responses.push(rx);
+                }
+
+                let _ = response_sender.send(responses);
+            }
         }
     }
 }
 
+/// Cloning a handle is cheap: clones share the plant's command channel and each gets its own
+/// update receiver. Handles are `Send + Sync`, clone one into each task issuing requests.
 pub struct RithmicHistoryPlantHandle {
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
     sender: tokio::sync::mpsc::Sender<HistoryPlantCommand>,
     // Used for cloning
     subscription_sender: tokio::sync::broadcast::Sender<RithmicResponse>,
     pub subscription_receiver: tokio::sync::broadcast::Receiver<RithmicResponse>,
+    skipped_updates: u64,
 }
 
 impl RithmicHistoryPlantHandle {
+    /// Next update from the plant, None once the plant has stopped.
+    /// When this handle reads slower than the updates arrive, the oldest ones are skipped
+    /// instead of failing, the count is logged and kept in `skipped_updates`.
+    pub async fn recv_update(&mut self) -> Option<RithmicResponse> {
+        loop {
+            match self.subscription_receiver.recv().await {
+                Ok(response) => return Some(response),
+                Err(RecvError::Lagged(skipped)) => {
+                    event!(Level::WARN, "history_plant: receiver lagged, skipped {} updates", skipped);
+
+                    self.skipped_updates += skipped;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Total number of updates this handle skipped because it was lagging
+    pub fn skipped_updates(&self) -> u64 {
+        self.skipped_updates
+    }
+
+    /// Last raw frames sent and received by the plant, empty unless
+    /// `RithmicConnectionInfo::capture_raw_frames` is set
+    pub fn recent_frames(&self) -> Vec<CapturedFrame> {
+        self.frame_capture
+            .as_ref()
+            .map(|frame_capture| frame_capture.recent_frames())
+            .unwrap_or_default()
+    }
+
+    /// Since when the plant has been connected, how many times it reconnected and its last
+    /// connection or server error
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_monitor.stats()
+    }
+
     pub async fn login(&self) -> Result<RithmicResponse, String> {
         event!(Level::INFO, "history_plant: logging in");
 
@@ -441,103 +685,210 @@ impl RithmicHistoryPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let response = rx.await.unwrap()?.remove(0);
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        if response.error.is_none() {
-            let _ = self.sender.send(HistoryPlantCommand::SetLogin).await;
+        match rx.await.map_err(not_connected)? {
+            Ok(mut responses) => {
+                let _ = self.sender.send(HistoryPlantCommand::SetLogin).await;
 
-            event!(Level::INFO, "history_plant: logged in");
+                event!(Level::INFO, "history_plant: logged in");
 
-            Ok(response)
-        } else {
-            event!(
-                Level::ERROR,
-                "history_plant: login failed {:?}",
-                response.error
-            );
+                Ok(responses.remove(0))
+            }
+            Err(e) => {
+                event!(Level::ERROR, "history_plant: login failed {}", e);
 
-            Err(response.error.unwrap())
+                Err(e)
+            }
         }
     }
 
+    /// Unsubscribe the bar feeds, then log out and close the connection
     pub async fn disconnect(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel();
+
+        let _ = self
+            .sender
+            .send(HistoryPlantCommand::UnsubscribeAll { response_sender: tx })
+            .await;
+
+        if let Ok(responses) = rx.await {
+            await_unsubscribes("history_plant", responses).await;
+        }
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = HistoryPlantCommand::Logout {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let mut r = rx.await.unwrap()?;
+        self.sender.send(command).await.map_err(not_connected)?;
+        let mut r = rx.await.map_err(not_connected)??;
         let _ = self.sender.send(HistoryPlantCommand::Close).await;
         let response = r.remove(0);
 
-        self.subscription_sender.send(response.clone()).unwrap();
+        let _ = self.subscription_sender.send(response.clone());
 
         Ok(response)
     }
 
     pub async fn get_historical_tick_bar(
         &self,
-        symbol: String,
-        exchange: String,
-        bar_type: request_tick_bar_replay::BarType,
-        bar_sub_type: request_tick_bar_replay::BarSubType,
-        bar_type_specifier: String,
-        start_index: i32,
-        finish_index: i32,
+        range: RithmicTickBarRange,
         direction: request_tick_bar_replay::Direction,
         time_order: request_tick_bar_replay::TimeOrder,
     ) -> Result<Vec<RithmicResponse>, String> {
+        validate_bar_type_specifier(range.bar_type, &range.bar_type_specifier)?;
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = HistoryPlantCommand::GetHistoricalTickBar {
-            symbol,
-            exchange,
-            bar_type,
-            bar_sub_type,
-            bar_type_specifier,
-            start_index,
-            finish_index,
+            symbol: range.symbol,
+            exchange: range.exchange,
+            bar_type: range.bar_type,
+            bar_sub_type: range.bar_sub_type,
+            bar_type_specifier: range.bar_type_specifier,
+            start_index: range.start_index,
+            finish_index: range.finish_index,
             direction,
             time_order,
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?)
+        rx.await.map_err(not_connected)?
     }
 
+    /// The `start_index` and `finish_index` of `range`, like the `marker` of the returned bars,
+    /// are seconds since the epoch in UTC, not exchange local time. The protocol carries no
+    /// exchange timezone, convert the marker with the timezone of the exchange when labelling
+    /// a chart.
     pub async fn get_historical_time_bar(
         &self,
-        symbol: String,
-        exchange: String,
-        bar_type: request_time_bar_replay::BarType,
-        bar_type_period: i32,
-        start_index: i32,
-        finish_index: i32,
+        range: RithmicTimeBarRange,
         direction: request_time_bar_replay::Direction,
         time_order: request_time_bar_replay::TimeOrder,
     ) -> Result<Vec<RithmicResponse>, String> {
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = HistoryPlantCommand::GetHistoricalTimeBar {
-            symbol,
-            exchange,
-            bar_type,
-            bar_type_period,
-            start_index,
-            finish_index,
+            symbol: range.symbol,
+            exchange: range.exchange,
+            bar_type: range.bar_type,
+            bar_type_period: range.bar_type_period,
+            start_index: range.start_index,
+            finish_index: range.finish_index,
             direction,
             time_order,
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        rx.await.map_err(not_connected)?
+    }
+
+    /// Resume a bar replay the server stopped at its bar limit, with the `request_key` carried
+    /// by its last response. The rest of the replay comes back the same way, its last response
+    /// carries a new key when the limit is hit again.
+    pub async fn resume_bars(&self, request_key: String) -> Result<Vec<RithmicResponse>, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = HistoryPlantCommand::ResumeBars {
+            request_key,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?)
+        rx.await.map_err(not_connected)?
+    }
+
+    /// The bars of `range` in full, resuming the replay as long as the server stops it at its
+    /// bar limit
+    async fn replay_time_bars(
+        &self,
+        range: RithmicTimeBarRange,
+    ) -> Result<Vec<ResponseTimeBarReplay>, String> {
+        let mut responses = self
+            .get_historical_time_bar(
+                range,
+                request_time_bar_replay::Direction::First,
+                request_time_bar_replay::TimeOrder::Forwards,
+            )
+            .await?;
+
+        let mut bars = vec![];
+
+        loop {
+            let request_key = match responses.last().map(|response| &response.message) {
+                Some(RithmicMessage::ResponseTimeBarReplay(last)) => last.request_key.clone(),
+                _ => None,
+            };
+
+            let received = bars.len();
+
+            bars.extend(responses.into_iter().filter_map(|response| match response.message {
+                // The last response of a replay only carries its status
+                RithmicMessage::ResponseTimeBarReplay(bar) if bar.marker.is_some() => Some(bar),
+                _ => None,
+            }));
+
+            match request_key {
+                // A resume without bars would be asked again forever
+                Some(request_key) if bars.len() > received => {
+                    responses = self.resume_bars(request_key).await?;
+                }
+                _ => return Ok(bars),
+            }
+        }
+    }
+
+    /// `get_historical_time_bar` over `range` as sequential replays of at most `chunk` each,
+    /// so a long range is never requested or held in memory at once. A replay the server stops
+    /// at its bar limit is resumed before moving to the next chunk. The bars of all the chunks
+    /// come out as one stream, oldest first.
+    pub fn get_historical_time_bar_chunked(
+        &self,
+        range: RithmicTimeBarRange,
+        chunk: Duration,
+    ) -> Result<impl Stream<Item = Result<ResponseTimeBarReplay, String>>, String> {
+        let chunk_secs = i32::try_from(chunk.as_secs())
+            .ok()
+            .filter(|secs| *secs > 0)
+            .ok_or_else(|| format!("invalid chunk {:?}, expected at least 1s", chunk))?;
+
+        // Finish indexes are inclusive, the next chunk starts one second after
+        let finish_index = range.finish_index;
+
+        let chunks: Vec<(i32, i32)> = (range.start_index..=finish_index)
+            .step_by(chunk_secs as usize)
+            .map(|from| (from, from.saturating_add(chunk_secs - 1).min(finish_index)))
+            .collect();
+
+        let handle = self.clone();
+
+        let bars = stream::iter(chunks).then(move |(from, to)| {
+            let handle = handle.clone();
+
+            let range = RithmicTimeBarRange {
+                start_index: from,
+                finish_index: to,
+                ..range.clone()
+            };
+
+            async move { handle.replay_time_bars(range).await }
+        });
+
+        Ok(bars.flat_map(|chunk| {
+            let bars: Vec<Result<ResponseTimeBarReplay, String>> = match chunk {
+                Ok(bars) => bars.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+
+            stream::iter(bars)
+        }))
     }
 
     pub async fn subscribe_tick_bar(
@@ -560,11 +911,13 @@ impl RithmicHistoryPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?.remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
+    /// The `marker` of the `TimeBar` updates is in seconds since the epoch in UTC, see
+    /// `get_historical_time_bar`
     pub async fn subscribe_time_bar(
         &self,
         symbol: &str,
@@ -583,18 +936,26 @@ impl RithmicHistoryPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?.remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 }
 
+/// The plant's actor stopped, e.g. its connection was closed or failed
+fn not_connected<E>(_: E) -> String {
+    plant_not_connected("history")
+}
+
 impl Clone for RithmicHistoryPlantHandle {
     fn clone(&self) -> Self {
         RithmicHistoryPlantHandle {
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
             sender: self.sender.clone(),
             subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
+            skipped_updates: 0,
         }
     }
 }
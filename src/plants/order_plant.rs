@@ -1,21 +1,44 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque, hash_map::Entry},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
 use async_trait::async_trait;
+use bytes::Bytes;
 use tracing::{event, Level};
 
 use crate::{
     api::{
-        RithmicConnectionInfo,
+        CredentialsProvider, RithmicConnectionInfo, RithmicCredentials, UnheardUpdates,
+        frame_capture::{CapturedFrame, FrameCapture},
         receiver_api::{RithmicReceiverApi, RithmicResponse},
-        rithmic_command_types::{RithmicBracketOrder, RithmicCancelOrder, RithmicModifyOrder},
+        rithmic_command_types::{
+            BracketLeg, OrderAck, OrderLatencyStats, OrderPlacement, OrderRejection, RithmicAccount, RithmicAutoLiquidation,
+            RithmicBracketOrder, RithmicCancelOrder, RithmicFill, RithmicHistoricalOrder,
+            RithmicModifyOrder, RithmicNetPosition, RithmicOrder, RithmicTradeRoute,
+            total_unfilled_size,
+        },
         sender_api::RithmicSenderApi,
     },
     request_handler::{RithmicRequest, RithmicRequestHandler},
-    rti::request_login::SysInfraType,
-    ws::{get_heartbeat_interval, PlantActor, RithmicStream, connect},
+    rti::{
+        messages::RithmicMessage,
+        request_login::SysInfraType,
+        response_list_exchange_permissions::EntitlementFlag,
+        exchange_order_notification,
+        rithmic_order_notification::{NotifyType, PriceType},
+        ExchangeOrderNotification, RithmicOrderNotification,
+    },
+    ws::{ConnectionMonitor, ConnectionStats, IdleTimer, ServerClock, drain_updates, get_heartbeat_interval, plant_not_connected, wait_for_update, PlantActor, RithmicStream, connect_with_failover},
 };
 
 use futures_util::{
-    stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
+    stream::{self, SplitSink, SplitStream},
+    SinkExt, Stream, StreamExt,
 };
 
 use tokio_tungstenite::{
@@ -27,11 +50,37 @@ use tokio_tungstenite::{
 
 use tokio::{
     net::TcpStream,
-    sync::{broadcast::Sender, oneshot},
-    time::Interval,
+    sync::{
+        broadcast::{self, Sender},
+        mpsc::error::TrySendError,
+        Mutex, oneshot,
+    },
+    time::{Instant, Interval},
 };
 
+/// Error returned for requests pending when the connection is lost
+pub const CONNECTION_LOST: &str = "connection lost";
+
+/// Error returned for new orders while trading is halted, see `halt_trading`
+pub const TRADING_HALTED: &str = "trading halted";
+
+/// Set while new orders are blocked, shared by the plant and its handles
+type TradingHalt = Arc<AtomicBool>;
+
+/// Held for the whole of a reconnect so the handles run one at a time
+type ReconnectLock = Arc<Mutex<()>>;
+
+type OrderResponseReceiver = oneshot::Receiver<Result<Vec<RithmicResponse>, String>>;
+
+/// A cancel waiting for the basket id of the order it targets, with its `auto` flag
+type PendingCancel = (Option<bool>, oneshot::Sender<Result<Vec<RithmicResponse>, String>>);
+
 pub enum OrderPlantCommand {
+    AccountList {
+        fcm_id: String,
+        ib_id: String,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
     Close,
     Login {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
@@ -47,6 +96,9 @@ pub enum OrderPlantCommand {
     SubscribeBracketUpdates {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    SubscribeAccountRmsUpdates {
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
     SubscribePnlUpdates {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
@@ -54,6 +106,22 @@ pub enum OrderPlantCommand {
         bracket_order: RithmicBracketOrder,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    EncodeOrder {
+        order: RithmicOrder,
+        response_sender: oneshot::Sender<Result<(Bytes, String), String>>,
+    },
+    PlaceOrders {
+        orders: Vec<RithmicOrder>,
+        response_sender: oneshot::Sender<Result<Vec<OrderResponseReceiver>, String>>,
+    },
+    /// Nobody waits for the response, a rejection is only logged
+    PlaceDetachedOrder {
+        order: RithmicOrder,
+    },
+    ChainOrder {
+        trigger_basket_id: String,
+        order: RithmicOrder,
+    },
     ModifyOrder {
         order: RithmicModifyOrder,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
@@ -70,37 +138,178 @@ pub enum OrderPlantCommand {
     },
     CancelOrder {
         order_id: String,
+        auto: Option<bool>,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    CancelOrderByTag {
+        user_tag: String,
+        auto: Option<bool>,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    ExchangePermissions {
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    TradeRoutes {
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    CancelAllOrders {
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    CancelBracketLeg {
+        parent_basket_id: String,
+        leg: BracketLeg,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
     ShowOrders {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    ResolveBasketId {
+        request_id: String,
+        response_sender: oneshot::Sender<Option<String>>,
+    },
+    ResolveByTag {
+        user_tag: String,
+        response_sender: oneshot::Sender<Option<String>>,
+    },
+    OrderLatency {
+        basket_id: String,
+        response_sender: oneshot::Sender<Option<Duration>>,
+    },
+    OrderLatencyStats {
+        response_sender: oneshot::Sender<Option<OrderLatencyStats>>,
+    },
+    ReplayExecutions {
+        start_index: i32,
+        finish_index: i32,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    ShowOrderHistory {
+        basket_id: Option<String>,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    ShowOrderHistoryDates {
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    ShowOrderHistorySummary {
+        date: String,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    Reconnect {
+        response_sender: oneshot::Sender<Result<(), String>>,
+    },
+    ListSubscriptions {
+        response_sender: oneshot::Sender<Vec<OrderPlantSubscription>>,
+    },
+    SelectAccount {
+        fcm_id: String,
+        ib_id: String,
+        account_id: String,
+    },
+    SetCredentialsProvider {
+        provider: Option<CredentialsProvider>,
+    },
+    SetOrderPlacement {
+        order_placement: OrderPlacement,
+    },
+}
+
+/// Number of completed orders the registry keeps for lookups, the oldest are forgotten first
+const COMPLETED_ORDERS_KEPT: usize = 1024;
+
+/// Correlation between the request ids and user tags of orders and the basket ids
+/// the server assigned to them
+#[derive(Debug, Default)]
+struct OrderRegistry {
+    basket_by_request: HashMap<String, String>,
+    basket_by_tag: HashMap<String, String>,
+    /// Basket ids of the completed orders still kept, oldest first
+    completed: VecDeque<String>,
+    /// Round-trip latency of the orders accepted by the exchange
+    latency_by_basket: HashMap<String, Duration>,
+    /// When the new order request was sent, by basket id once the server assigned one
+    sent_at_by_basket: HashMap<String, Instant>,
+    sent_at_by_request: HashMap<String, Instant>,
+}
+
+impl OrderRegistry {
+    /// Record that an order is filled, cancelled or rejected. Its ids stay known until
+    /// `COMPLETED_ORDERS_KEPT` newer orders complete.
+    fn complete(&mut self, basket_id: &str) {
+        self.sent_at_by_basket.remove(basket_id);
+
+        if self.completed.iter().any(|completed| completed == basket_id) {
+            return;
+        }
+
+        self.completed.push_back(basket_id.to_string());
+
+        while self.completed.len() > COMPLETED_ORDERS_KEPT {
+            if let Some(forgotten) = self.completed.pop_front() {
+                self.basket_by_request.retain(|_, basket_id| *basket_id != forgotten);
+                self.basket_by_tag.retain(|_, basket_id| *basket_id != forgotten);
+                self.latency_by_basket.remove(&forgotten);
+            }
+        }
+    }
+}
+
+/// Subscriptions that are re-issued by `RithmicOrderPlantHandle::reconnect`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderPlantSubscription {
+    OrderUpdates,
+    BracketUpdates,
+    AccountRmsUpdates,
 }
 
 pub struct RithmicOrderPlant {
     pub connection_handle: tokio::task::JoinHandle<()>,
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
+    read_retries: usize,
+    reconnect_lock: ReconnectLock,
     sender: tokio::sync::mpsc::Sender<OrderPlantCommand>,
+    server_clock: ServerClock,
     subscription_sender: Sender<RithmicResponse>,
+    trading_halted: TradingHalt,
 }
 
 impl RithmicOrderPlant {
+    /// Panics when the gateway can't be reached, see `try_new`
     pub async fn new(conn_info: &RithmicConnectionInfo) -> RithmicOrderPlant {
+        Self::try_new(conn_info).await.unwrap()
+    }
+
+    /// Connect to the gateway, failing instead of panicking so an application can carry on
+    /// without this plant
+    pub async fn try_new(conn_info: &RithmicConnectionInfo) -> Result<RithmicOrderPlant, String> {
         let (req_tx, req_rx) = tokio::sync::mpsc::channel::<OrderPlantCommand>(32);
-        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(1024);
+        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(conn_info.update_buffer_size.max(1));
 
-        let mut order_plant = OrderPlant::new(req_rx, sub_tx.clone(), conn_info)
-            .await
-            .unwrap();
+        let frame_capture = FrameCapture::from_capacity("order_plant", conn_info.capture_raw_frames);
+
+        let mut order_plant = OrderPlant::new(req_rx, sub_tx.clone(), conn_info, frame_capture.clone())
+            .await?;
+
+        let server_clock = order_plant.server_clock.clone();
+        let trading_halted = order_plant.trading_halted.clone();
+
+        let connection_monitor = order_plant.connection_monitor.clone();
 
         let connection_handle = tokio::spawn(async move {
             order_plant.run().await;
         });
 
-        RithmicOrderPlant {
+        Ok(RithmicOrderPlant {
             connection_handle,
+            connection_monitor,
+            frame_capture,
+            read_retries: conn_info.read_retries,
+            reconnect_lock: ReconnectLock::default(),
             sender: req_tx,
+            server_clock,
             subscription_sender: sub_tx,
-        }
+            trading_halted,
+        })
     }
 }
 
@@ -109,16 +318,36 @@ impl RithmicStream for RithmicOrderPlant {
 
     fn get_handle(&self) -> RithmicOrderPlantHandle {
         RithmicOrderPlantHandle {
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
+            read_retries: self.read_retries,
+            reconnect_lock: self.reconnect_lock.clone(),
             sender: self.sender.clone(),
+            server_clock: self.server_clock.clone(),
+            subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
+            trading_halted: self.trading_halted.clone(),
         }
     }
 }
 
 pub struct OrderPlant {
+    /// Open child orders of each bracket, keyed by the parent basket id
+    bracket_legs: HashMap<String, HashMap<BracketLeg, String>>,
+    closing: bool,
     config: RithmicConnectionInfo,
+    /// False from the loss of the connection until `Reconnect` opens a new one
+    connected: bool,
+    connection_monitor: ConnectionMonitor,
+    credentials_provider: Option<CredentialsProvider>,
+    idle_timer: IdleTimer,
     interval: Interval,
     logged_in: bool,
+    /// Follow-up orders sent once the order with the basket id is completely filled
+    order_chains: HashMap<String, Vec<RithmicOrder>>,
+    order_registry: OrderRegistry,
+    /// Cancels by user tag sent before the server assigned the order its basket id
+    pending_cancels: HashMap<String, PendingCancel>,
     request_handler: RithmicRequestHandler,
     request_receiver: tokio::sync::mpsc::Receiver<OrderPlantCommand>,
     rithmic_reader: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
@@ -128,7 +357,10 @@ pub struct OrderPlant {
         Message,
     >,
     rithmic_sender_api: RithmicSenderApi,
+    server_clock: ServerClock,
     subscription_sender: Sender<RithmicResponse>,
+    subscriptions: HashSet<OrderPlantSubscription>,
+    trading_halted: TradingHalt,
 }
 
 impl OrderPlant {
@@ -136,31 +368,297 @@ impl OrderPlant {
         request_receiver: tokio::sync::mpsc::Receiver<OrderPlantCommand>,
         subscription_sender: Sender<RithmicResponse>,
         conn_info: &RithmicConnectionInfo,
+        frame_capture: Option<FrameCapture>,
     ) -> Result<OrderPlant, String> {
-        let config = conn_info.clone();
+        let mut config = conn_info.clone();
 
-        let ws_stream = connect(&config.url).await.unwrap();
+        let (ws_stream, url) = connect_with_failover(&config.gateway_urls(), &config.ws_headers)
+            .await
+            .map_err(|e| format!("order_plant: {}", e))?;
+        // Reconnect to the gateway that worked first
+        config.url = url;
         let (rithmic_sender, rithmic_reader) = ws_stream.split();
-        let rithmic_sender_api = RithmicSenderApi::new(&config);
+        let mut rithmic_sender_api = RithmicSenderApi::new(&config);
+        rithmic_sender_api.set_frame_capture(frame_capture.clone());
+
         let rithmic_receiver_api = RithmicReceiverApi {
             source: "order_plant".to_string(),
+            frame_capture,
+            template_version: config.template_version.clone(),
         };
 
+        let idle_timer = IdleTimer::new(config.auto_logout_after_secs);
         let interval = get_heartbeat_interval();
 
         Ok(OrderPlant {
+            bracket_legs: HashMap::new(),
+            closing: false,
             config,
+            connected: true,
+            connection_monitor: ConnectionMonitor::default(),
+            credentials_provider: None,
+            idle_timer,
             interval,
             logged_in: false,
+            order_chains: HashMap::new(),
+            order_registry: OrderRegistry::default(),
+            pending_cancels: HashMap::new(),
             request_handler: RithmicRequestHandler::new(),
             request_receiver,
             rithmic_reader,
             rithmic_receiver_api,
             rithmic_sender_api,
             rithmic_sender,
+            server_clock: ServerClock::default(),
             subscription_sender,
+            subscriptions: HashSet::new(),
+            trading_halted: TradingHalt::default(),
         })
     }
+
+    /// Returns true if the plant should stop, i.e. the close was requested by us.
+    /// Otherwise the plant keeps running so the handle can reconnect.
+    fn connection_lost(&mut self) -> bool {
+        self.connected = false;
+
+        if !self.closing {
+            event!(Level::WARN, "order_plant: connection lost, waiting for reconnect");
+
+            self.connection_monitor.error(CONNECTION_LOST);
+
+            self.logged_in = false;
+            self.request_handler.fail_pending(CONNECTION_LOST);
+
+            for (_, (_, response_sender)) in self.pending_cancels.drain() {
+                let _ = response_sender.send(Err(CONNECTION_LOST.to_string()));
+            }
+
+            // The orders sent on the lost connection won't be answered
+            self.order_registry.sent_at_by_request.clear();
+        }
+
+        self.closing
+    }
+
+    /// Send a frame to the server. While the connection is lost, or when the send fails, the
+    /// pending requests, including the one just registered, fail with `CONNECTION_LOST`
+    async fn send_frame(&mut self, message: Message) {
+        if self.connected {
+            match self.rithmic_sender.send(message).await {
+                Ok(()) => return,
+                Err(e) => {
+                    event!(Level::ERROR, "order_plant: send failed {}", e);
+
+                    self.connection_lost();
+                }
+            }
+        }
+
+        self.request_handler.fail_pending(CONNECTION_LOST);
+    }
+
+    /// Record the basket id assigned to each request id and user tag
+    fn register_order_ids(&mut self, response: &RithmicResponse) {
+        let (basket_id, user_tag, request_id) = match &response.message {
+            RithmicMessage::ResponseNewOrder(r) => {
+                (&r.basket_id, &r.user_tag, Some(&response.request_id))
+            }
+            RithmicMessage::RithmicOrderNotification(n) => (&n.basket_id, &n.user_tag, None),
+            _ => return,
+        };
+
+        // Removed even when the order is rejected without a basket id
+        let sent_at = request_id
+            .and_then(|request_id| self.order_registry.sent_at_by_request.remove(request_id));
+
+        let Some(basket_id) = basket_id else {
+            return;
+        };
+
+        if let Some(request_id) = request_id {
+            self.order_registry
+                .basket_by_request
+                .insert(request_id.clone(), basket_id.clone());
+
+            if let Some(sent_at) = sent_at {
+                self.order_registry
+                    .sent_at_by_basket
+                    .insert(basket_id.clone(), sent_at);
+            }
+        }
+
+        if let Some(user_tag) = user_tag {
+            self.order_registry
+                .basket_by_tag
+                .insert(user_tag.clone(), basket_id.clone());
+        }
+    }
+
+    /// Send the cancels waiting for the basket id of their order now that the server assigned it,
+    /// or fail them if the order was rejected without one
+    async fn send_pending_cancels(&mut self, response: &RithmicResponse) {
+        self.pending_cancels
+            .retain(|_, (_, response_sender)| !response_sender.is_closed());
+
+        if let RithmicMessage::ResponseNewOrder(resp) = &response.message
+            && resp.basket_id.is_none()
+            && let Some(error) = &response.error
+            && let Some(user_tag) = &resp.user_tag
+            && let Some((_, response_sender)) = self.pending_cancels.remove(user_tag)
+        {
+            let _ = response_sender.send(Err(format!("order {} rejected: {}", user_tag, error)));
+        }
+
+        let ready: Vec<(String, String)> = self
+            .pending_cancels
+            .keys()
+            .filter_map(|user_tag| {
+                let basket_id = self.order_registry.basket_by_tag.get(user_tag)?;
+
+                Some((user_tag.clone(), basket_id.clone()))
+            })
+            .collect();
+
+        for (user_tag, basket_id) in ready {
+            let Some((auto, response_sender)) = self.pending_cancels.remove(&user_tag) else {
+                continue;
+            };
+
+            self.handle_command(OrderPlantCommand::CancelOrder {
+                order_id: basket_id,
+                auto,
+                response_sender,
+            })
+            .await;
+        }
+    }
+
+    /// Latency of an order placed by this session, on the first exchange notification of its
+    /// basket. A rejected order has no latency.
+    fn track_order_latency(&mut self, notification: &ExchangeOrderNotification) {
+        let Some(basket_id) = &notification.basket_id else {
+            return;
+        };
+
+        let Some(sent_at) = self.order_registry.sent_at_by_basket.remove(basket_id) else {
+            return;
+        };
+
+        if notification.notify_type() != exchange_order_notification::NotifyType::Reject {
+            self.order_registry
+                .latency_by_basket
+                .insert(basket_id.clone(), sent_at.elapsed());
+        }
+    }
+
+    /// Send the follow-up orders chained to an order once it is completely filled, or drop them
+    /// if it is cancelled or rejected first
+    async fn trigger_order_chain(&mut self, notification: &ExchangeOrderNotification) {
+        use exchange_order_notification::NotifyType;
+
+        let Some(basket_id) = &notification.basket_id else {
+            return;
+        };
+
+        if !self.order_chains.contains_key(basket_id) {
+            return;
+        }
+
+        match notification.notify_type() {
+            NotifyType::Fill if total_unfilled_size(notification) == Some(0) => {
+                if self.trading_halted.load(Ordering::SeqCst) {
+                    let orders = self.order_chains.remove(basket_id).unwrap_or_default();
+
+                    event!(
+                        Level::WARN,
+                        "order_plant: order {} filled while trading is halted, dropping {} chained orders",
+                        basket_id,
+                        orders.len()
+                    );
+
+                    return;
+                }
+
+                for order in self.order_chains.remove(basket_id).unwrap_or_default() {
+                    event!(
+                        Level::INFO,
+                        "order_plant: order {} filled, sending chained order {}",
+                        basket_id,
+                        order.localid
+                    );
+
+                    self.handle_command(OrderPlantCommand::PlaceDetachedOrder { order }).await;
+                }
+            }
+            NotifyType::Cancel | NotifyType::Reject => {
+                let orders = self.order_chains.remove(basket_id).unwrap_or_default();
+
+                event!(
+                    Level::WARN,
+                    "order_plant: order {} not filled, dropping {} chained orders",
+                    basket_id,
+                    orders.len()
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep track of the open target and stop of each bracket from the order notifications
+    fn track_bracket_leg(&mut self, notification: &RithmicOrderNotification) {
+        let (Some(parent), Some(basket_id)) =
+            (&notification.original_basket_id, &notification.basket_id)
+        else {
+            return;
+        };
+
+        if parent == basket_id {
+            return;
+        }
+
+        let leg = match notification.price_type() {
+            PriceType::Limit => BracketLeg::Target,
+            PriceType::StopLimit | PriceType::StopMarket => BracketLeg::Stop,
+            PriceType::Market => return,
+        };
+
+        if notification.notify_type() == NotifyType::Complete {
+            if let Some(legs) = self.bracket_legs.get_mut(parent) {
+                legs.retain(|_, child| child != basket_id);
+
+                if legs.is_empty() {
+                    self.bracket_legs.remove(parent);
+                }
+            }
+        } else {
+            self.bracket_legs
+                .entry(parent.clone())
+                .or_default()
+                .insert(leg, basket_id.clone());
+        }
+    }
+
+    /// Log out and close the connection once no request has been sent for `auto_logout_after_secs`
+    async fn auto_logout(&mut self) {
+        event!(
+            Level::WARN,
+            "order_plant: no request for {}s, logging out",
+            self.config.auto_logout_after_secs
+        );
+
+        self.logged_in = false;
+
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        self.handle_command(OrderPlantCommand::Logout { response_sender: tx }).await;
+        self.handle_command(OrderPlantCommand::Close).await;
+
+        tokio::spawn(async move {
+            if let Ok(Err(e)) = rx.await {
+                event!(Level::ERROR, "order_plant: auto logout failed: {}", e);
+            }
+        });
+    }
 }
 
 #[async_trait]
@@ -171,15 +669,31 @@ impl PlantActor for OrderPlant {
         loop {
             tokio::select! {
                 _ = self.interval.tick() => {
-                    if self.logged_in {
+                    if self.logged_in && self.idle_timer.expired() {
+                        self.auto_logout().await;
+                    } else if self.logged_in {
                         self.handle_command(OrderPlantCommand::SendHeartbeat {}).await;
                     }
                 }
                 Some(message) = self.request_receiver.recv() => {
+                    self.idle_timer.touch();
+
                     self.handle_command(message).await;
+
+                    // Nothing is left to read from a lost connection, stop here when closing
+                    if self.closing && !self.connected {
+                        break;
+                    }
                 }
-                Some(message) = self.rithmic_reader.next() => {
-                    let stop = self.handle_rithmic_message(message).await.unwrap();
+                message = self.rithmic_reader.next(), if self.connected => {
+                    let stop = match message {
+                        Some(message) => self.handle_rithmic_message(message).await.unwrap(),
+                        None => {
+                            event!(Level::INFO, "order_plant: end of stream");
+
+                            self.connection_lost()
+                        }
+                    };
 
                     if stop {
                         break;
@@ -204,24 +718,73 @@ impl PlantActor for OrderPlant {
                     frame
                 );
 
-                stop = true;
+                stop = self.connection_lost();
             }
             Ok(Message::Binary(data)) => match self.rithmic_receiver_api.buf_to_message(data) {
                 Ok(response) => {
-                    if response.is_update {
-                        self.subscription_sender.send(response).unwrap();
-                    } else {
-                        self.request_handler.handle_response(response);
+                    if let RithmicMessage::ResponseHeartbeat(heartbeat) = &response.message {
+                        self.server_clock.heartbeat_received(heartbeat.ssboe, heartbeat.usecs);
+                    }
+
+                    self.register_order_ids(&response);
+
+                    if !self.pending_cancels.is_empty() {
+                        self.send_pending_cancels(&response).await;
+                    }
+
+                    // Notifications replayed by history requests are past events, they must not
+                    // trigger chained orders again
+                    if let RithmicMessage::ExchangeOrderNotification(notification) = &response.message
+                        && notification.is_snapshot != Some(true)
+                    {
+                        self.track_order_latency(notification);
+                        self.trigger_order_chain(notification).await;
+                    }
+
+                    if let RithmicMessage::RithmicOrderNotification(notification) = &response.message {
+                        self.track_bracket_leg(notification);
+
+                        if notification.notify_type() == NotifyType::Complete
+                            && let Some(basket_id) = &notification.basket_id
+                        {
+                            self.order_registry.complete(basket_id);
+                        }
+                    }
+
+                    if let RithmicMessage::AccountRmsUpdates(update) = &response.message {
+                        event!(
+                            Level::WARN,
+                            "order_plant: auto liquidation update {:?}",
+                            RithmicAutoLiquidation::from_update(update)
+                        );
+                    }
+
+                    if self.request_handler.dispatch(response, &self.subscription_sender)
+                        && self.config.unheard_updates == UnheardUpdates::Close
+                    {
+                        event!(Level::WARN, "order_plant: no update receiver left, closing");
+
+                        self.handle_command(OrderPlantCommand::Close).await;
                     }
                 }
                 Err(e) => {
                     event!(Level::ERROR, "order_plant: response from server: {:?}", e);
+
+                    self.connection_monitor.error(&e);
                 }
             },
             Err(Error::ConnectionClosed) => {
                 event!(Level::INFO, "order_plant: Connection closed");
 
-                stop = true;
+                stop = self.connection_lost();
+            }
+            // The stream can't be read any further after an error
+            Err(e) => {
+                event!(Level::ERROR, "order_plant: read failed {}", e);
+
+                self.connection_monitor.error(&e);
+
+                stop = self.connection_lost();
             }
             _ => {
                 event!(Level::WARN, "order_plant: Unhandled message: {:?}", message);
@@ -233,18 +796,40 @@ impl PlantActor for OrderPlant {
 
     async fn handle_command(&mut self, command: OrderPlantCommand) {
         match command {
+            OrderPlantCommand::AccountList {
+                fcm_id,
+                ib_id,
+                response_sender,
+            } => {
+                let (req_buf, id) = self.rithmic_sender_api.request_account_list(&fcm_id, &ib_id);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
             OrderPlantCommand::Close => {
-                self.rithmic_sender
-                    .send(Message::Close(None))
-                    .await
-                    .unwrap();
+                self.closing = true;
+
+                self.send_frame(Message::Close(None)).await;
             }
             OrderPlantCommand::Login { response_sender } => {
+                let credentials = match &self.credentials_provider {
+                    Some(provider) => provider(),
+                    None => RithmicCredentials {
+                        user: self.config.user.clone(),
+                        password: self.config.password.clone(),
+                        region: None,
+                    },
+                };
+
                 let (login_buf, id) = self.rithmic_sender_api.request_login(
                     &self.config.system_name,
                     SysInfraType::OrderPlant,
-                    &self.config.user,
-                    &self.config.password,
+                    &credentials.user,
+                    &credentials.password,
                 );
 
                 event!(Level::INFO, "order_plant: sending login request {}", id);
@@ -254,10 +839,7 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(login_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(login_buf)).await;
             }
             OrderPlantCommand::SetLogin => {
                 self.logged_in = true;
@@ -270,20 +852,26 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(logout_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(logout_buf)).await;
             }
             OrderPlantCommand::SendHeartbeat {} => {
                 let (heartbeat_buf, _id) = self.rithmic_sender_api.request_heartbeat();
 
+                self.server_clock.heartbeat_sent();
+
                 let _ = self
                     .rithmic_sender
                     .send(Message::Binary(heartbeat_buf))
                     .await;
             }
             OrderPlantCommand::SubscribeOrderUpdates { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                self.subscriptions.insert(OrderPlantSubscription::OrderUpdates);
+
                 let (req_buf, id) = self
                     .rithmic_sender_api
                     .request_subscribe_for_order_updates();
@@ -293,12 +881,16 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
             }
             OrderPlantCommand::SubscribeBracketUpdates { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                self.subscriptions.insert(OrderPlantSubscription::BracketUpdates);
+
                 let (req_buf, id) = self
                     .rithmic_sender_api
                     .request_subscribe_to_bracket_updates();
@@ -308,15 +900,34 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::SubscribeAccountRmsUpdates { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                self.subscriptions.insert(OrderPlantSubscription::AccountRmsUpdates);
+
+                let (req_buf, id) = self.rithmic_sender_api.request_account_rms_updates();
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
             }
             OrderPlantCommand::PlaceBracketOrder {
                 bracket_order,
                 response_sender,
             } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
                 let (req_buf, id) = self.rithmic_sender_api.request_bracket_order(bracket_order);
 
                 self.request_handler.register_request(RithmicRequest {
@@ -324,74 +935,280 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::EncodeOrder {
+                order,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                // Encode with a copy so the message id isn't consumed and the frame isn't captured
+                let mut sender_api = self.rithmic_sender_api.clone();
+                sender_api.set_frame_capture(None);
+
+                let encoded = sender_api.request_new_order(&order);
+
+                let _ = response_sender.send(Ok(encoded));
+            }
+            OrderPlantCommand::PlaceOrders {
+                orders,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                // The whole batch is sent from this single command so no other
+                // request can be interleaved between the legs
+                let mut receivers = Vec::with_capacity(orders.len());
+
+                for order in orders {
+                    let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                    let (req_buf, id) = self.rithmic_sender_api.request_new_order(&order);
+
+                    self.order_registry.sent_at_by_request.insert(id.clone(), Instant::now());
+
+                    // The rejection code tells the caller why the order was refused
+                    self.request_handler
+                        .register_request_keeping_rejections(RithmicRequest {
+                            request_id: id,
+                            responder: tx,
+                        });
+
+                    self.send_frame(Message::Binary(req_buf)).await;
+
+                    receivers.push(rx);
+                }
+
+                let _ = response_sender.send(Ok(receivers));
+            }
+            OrderPlantCommand::ChainOrder {
+                trigger_basket_id,
+                order,
+            } => {
+                self.order_chains
+                    .entry(trigger_basket_id)
+                    .or_default()
+                    .push(order);
+            }
+            OrderPlantCommand::PlaceDetachedOrder { order } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    event!(Level::ERROR, "order_plant: order {} not sent: {}", order.localid, e);
+                    return;
+                }
+
+                let (req_buf, id) = self.rithmic_sender_api.request_new_order(&order);
+
+                self.order_registry.sent_at_by_request.insert(id.clone(), Instant::now());
+
+                let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                self.request_handler
+                    .register_request_keeping_rejections(RithmicRequest {
+                        request_id: id,
+                        responder: tx,
+                    });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+
+                tokio::spawn(async move {
+                    let rejection = match rx.await {
+                        Ok(Ok(responses)) => {
+                            responses.iter().find_map(|response| match &response.message {
+                                RithmicMessage::ResponseNewOrder(resp) => {
+                                    OrderRejection::from_response(resp)
+                                }
+                                _ => None,
+                            })
+                        }
+                        Ok(Err(e)) => Some(OrderRejection::from(e)),
+                        Err(_) => None,
+                    };
+
+                    if let Some(rejection) = rejection {
+                        event!(
+                            Level::ERROR,
+                            "order_plant: order {} rejected {}",
+                            order.localid,
+                            rejection
+                        );
+                    }
+                });
             }
             OrderPlantCommand::ModifyOrder {
                 order,
                 response_sender,
             } => {
-                let (req_buf, id) = self.rithmic_sender_api.request_modify_order(
-                    &order.id,
-                    &order.exchange,
-                    &order.symbol,
-                    order.qty,
-                    order.price,
-                    order.ordertype,
-                );
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self.rithmic_sender_api.request_modify_order(&order);
 
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
             }
-            OrderPlantCommand::CancelOrder {
-                order_id,
-                response_sender,
-            } => {
-                let (req_buf, id) = self.rithmic_sender_api.request_cancel_order(&order_id);
+            OrderPlantCommand::ExchangePermissions { response_sender } => {
+                let (req_buf, id) = self.rithmic_sender_api.request_list_exchange_permissions();
 
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
             }
-            OrderPlantCommand::ModifyStop {
-                order_id,
-                ticks,
-                response_sender,
-            } => {
-                let (req_buf, id) = self
-                    .rithmic_sender_api
-                    .request_update_stop_bracket_level(&order_id, ticks);
+            OrderPlantCommand::TradeRoutes { response_sender } => {
+                let (req_buf, id) = self.rithmic_sender_api.request_trade_routes();
 
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
             }
-            OrderPlantCommand::ModifyProfit {
-                order_id,
+            OrderPlantCommand::CancelAllOrders { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self.rithmic_sender_api.request_cancel_all_orders();
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::CancelOrder {
+                order_id,
+                auto,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self.rithmic_sender_api.request_cancel_order(&order_id, auto);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::CancelOrderByTag {
+                user_tag,
+                auto,
+                response_sender,
+            } => match self.order_registry.basket_by_tag.get(&user_tag) {
+                Some(basket_id) => {
+                    let order_id = basket_id.clone();
+
+                    self.handle_command(OrderPlantCommand::CancelOrder {
+                        order_id,
+                        auto,
+                        response_sender,
+                    })
+                    .await;
+                }
+                None => {
+                    // A cancel whose caller gave up doesn't hold back a new one
+                    self.pending_cancels
+                        .retain(|_, (_, response_sender)| !response_sender.is_closed());
+
+                    match self.pending_cancels.entry(user_tag) {
+                        Entry::Occupied(pending) => {
+                            let _ = response_sender.send(Err(format!(
+                                "order {}: a cancel is already waiting for its basket id",
+                                pending.key()
+                            )));
+                        }
+                        Entry::Vacant(pending) => {
+                            pending.insert((auto, response_sender));
+                        }
+                    }
+                }
+            },
+            OrderPlantCommand::CancelBracketLeg {
+                parent_basket_id,
+                leg,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let Some(order_id) = self
+                    .bracket_legs
+                    .get(&parent_basket_id)
+                    .and_then(|legs| legs.get(&leg))
+                    .cloned()
+                else {
+                    let _ = response_sender.send(Err(format!(
+                        "no open {:?} leg for bracket {}",
+                        leg, parent_basket_id
+                    )));
+                    return;
+                };
+
+                let (req_buf, id) = self.rithmic_sender_api.request_cancel_order(&order_id, None);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ModifyStop {
+                order_id,
                 ticks,
                 response_sender,
             } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self
+                    .rithmic_sender_api
+                    .request_update_stop_bracket_level(&order_id, ticks);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ModifyProfit {
+                order_id,
+                ticks,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
                 let (req_buf, id) = self
                     .rithmic_sender_api
                     .request_update_target_bracket_level(&order_id, ticks);
@@ -401,12 +1218,115 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ResolveBasketId {
+                request_id,
+                response_sender,
+            } => {
+                let basket_id = self.order_registry.basket_by_request.get(&request_id).cloned();
+
+                let _ = response_sender.send(basket_id);
+            }
+            OrderPlantCommand::ResolveByTag {
+                user_tag,
+                response_sender,
+            } => {
+                let basket_id = self.order_registry.basket_by_tag.get(&user_tag).cloned();
+
+                let _ = response_sender.send(basket_id);
+            }
+            OrderPlantCommand::OrderLatency {
+                basket_id,
+                response_sender,
+            } => {
+                let latency = self.order_registry.latency_by_basket.get(&basket_id).copied();
+
+                let _ = response_sender.send(latency);
+            }
+            OrderPlantCommand::OrderLatencyStats { response_sender } => {
+                let latencies: Vec<Duration> =
+                    self.order_registry.latency_by_basket.values().copied().collect();
+
+                let _ = response_sender.send(OrderLatencyStats::from_latencies(&latencies));
+            }
+            OrderPlantCommand::ReplayExecutions {
+                start_index,
+                finish_index,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self
+                    .rithmic_sender_api
+                    .request_replay_executions(start_index, finish_index);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ShowOrderHistory {
+                basket_id,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self
+                    .rithmic_sender_api
+                    .request_show_order_history(basket_id.as_deref());
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ShowOrderHistoryDates { response_sender } => {
+                let (req_buf, id) = self.rithmic_sender_api.request_show_order_history_dates();
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::ShowOrderHistorySummary {
+                date,
+                response_sender,
+            } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
+                let (req_buf, id) = self
+                    .rithmic_sender_api
+                    .request_show_order_history_summary(&date);
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(req_buf)).await;
             }
             OrderPlantCommand::ShowOrders { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
                 let (req_buf, id) = self.rithmic_sender_api.request_show_orders();
 
                 self.request_handler.register_request(RithmicRequest {
@@ -414,22 +1334,131 @@ impl PlantActor for OrderPlant {
                     responder: response_sender,
                 });
 
-                self.rithmic_sender
-                    .send(Message::Binary(req_buf))
-                    .await
-                    .unwrap();
+                self.send_frame(Message::Binary(req_buf)).await;
+            }
+            OrderPlantCommand::Reconnect { response_sender } => {
+                event!(Level::INFO, "order_plant: reconnecting to {}", self.config.url);
+
+                match connect_with_failover(&self.config.gateway_urls(), &self.config.ws_headers).await {
+                    Ok((ws_stream, url)) => {
+                        let (rithmic_sender, rithmic_reader) = ws_stream.split();
+
+                        self.config.url = url;
+
+                        self.rithmic_sender = rithmic_sender;
+                        self.rithmic_reader = rithmic_reader;
+                        self.connected = true;
+                        self.logged_in = false;
+                        self.connection_monitor.reconnected();
+
+                        let _ = response_sender.send(Ok(()));
+                    }
+                    Err(e) => {
+                        event!(Level::ERROR, "order_plant: reconnect failed {}", e);
+
+                        self.connection_monitor.error(&e);
+
+                        let _ = response_sender.send(Err(e.to_string()));
+                    }
+                }
+            }
+            OrderPlantCommand::ListSubscriptions { response_sender } => {
+                let _ = response_sender.send(self.subscriptions.iter().copied().collect());
+            }
+            OrderPlantCommand::SelectAccount {
+                fcm_id,
+                ib_id,
+                account_id,
+            } => {
+                self.rithmic_sender_api.set_account(&fcm_id, &ib_id, &account_id);
+            }
+            OrderPlantCommand::SetCredentialsProvider { provider } => {
+                self.credentials_provider = provider;
+            }
+            OrderPlantCommand::SetOrderPlacement { order_placement } => {
+                self.rithmic_sender_api.set_order_placement(order_placement);
             }
             _ => {}
         };
     }
 }
 
+/// Cloning a handle is cheap: clones share the plant's command channel and each gets its own
+/// update receiver. Handles are `Send + Sync`, clone one into each task issuing requests.
 pub struct RithmicOrderPlantHandle {
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
+    read_retries: usize,
+    reconnect_lock: ReconnectLock,
     sender: tokio::sync::mpsc::Sender<OrderPlantCommand>,
+    server_clock: ServerClock,
+    subscription_sender: Sender<RithmicResponse>,
     pub subscription_receiver: tokio::sync::broadcast::Receiver<RithmicResponse>,
+    trading_halted: TradingHalt,
 }
 
 impl RithmicOrderPlantHandle {
+    /// Last raw frames sent and received by the plant, empty unless
+    /// `RithmicConnectionInfo::capture_raw_frames` is set
+    pub fn recent_frames(&self) -> Vec<CapturedFrame> {
+        self.frame_capture
+            .as_ref()
+            .map(|frame_capture| frame_capture.recent_frames())
+            .unwrap_or_default()
+    }
+
+    /// Since when the plant has been connected, how many times it reconnected and its last
+    /// connection or server error
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_monitor.stats()
+    }
+
+    /// Estimated gateway time minus local time in microseconds, positive when the gateway clock
+    /// is ahead. Updated on every heartbeat response, None until the first one is received.
+    pub fn server_time_offset(&self) -> Option<i64> {
+        self.server_clock.offset_usecs()
+    }
+
+    /// Block new orders from every handle of this plant until `resume_trading`, e.g. as a kill
+    /// switch. Placing an order or a bracket fails with `TRADING_HALTED` right away, chained
+    /// orders are dropped instead of sent. Cancels and modifies still go through.
+    pub fn halt_trading(&self) {
+        self.trading_halted.store(true, Ordering::SeqCst);
+
+        event!(Level::WARN, "order_plant: trading halted");
+    }
+
+    pub fn resume_trading(&self) {
+        self.trading_halted.store(false, Ordering::SeqCst);
+
+        event!(Level::INFO, "order_plant: trading resumed");
+    }
+
+    pub fn is_trading_halted(&self) -> bool {
+        self.trading_halted.load(Ordering::SeqCst)
+    }
+
+    fn check_trading(&self) -> Result<(), String> {
+        if self.is_trading_halted() {
+            Err(TRADING_HALTED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Wait for the first update on this handle's receiver matching `predicate`, e.g. the fill
+    /// of an order just placed. Updates received before the match are consumed.
+    pub async fn wait_for<F>(
+        &mut self,
+        predicate: F,
+        timeout: Duration,
+    ) -> Result<RithmicResponse, String>
+    where
+        F: Fn(&RithmicResponse) -> bool,
+    {
+        wait_for_update(&mut self.subscription_receiver, predicate, timeout).await
+    }
+
     pub async fn login(&self) -> Result<RithmicResponse, String> {
         event!(Level::INFO, "order_plant: logging in");
 
@@ -439,23 +1468,21 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let response = rx.await.unwrap().unwrap().remove(0);
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        if response.error.is_none() {
-            let _ = self.sender.send(OrderPlantCommand::SetLogin).await;
+        match rx.await.map_err(not_connected)? {
+            Ok(mut responses) => {
+                let _ = self.sender.send(OrderPlantCommand::SetLogin).await;
 
-            event!(Level::INFO, "order_plant: logged in");
+                event!(Level::INFO, "order_plant: logged in");
 
-            Ok(response)
-        } else {
-            event!(
-                Level::ERROR,
-                "order_plant: login failed {:?}",
-                response.error
-            );
+                Ok(responses.remove(0))
+            }
+            Err(e) => {
+                event!(Level::ERROR, "order_plant: login failed {}", e);
 
-            Err(response.error.unwrap())
+                Err(e)
+            }
         }
     }
 
@@ -466,13 +1493,119 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let mut r = rx.await.unwrap().unwrap();
+        self.sender.send(command).await.map_err(not_connected)?;
+        let mut r = rx.await.map_err(not_connected)??;
         let _ = self.sender.send(OrderPlantCommand::Close).await;
 
         Ok(r.remove(0))
     }
 
+    /// List the accounts of the user, fcm_id and ib_id are the ones returned by the login response.
+    /// Idempotent, re-sent after a reconnect up to `read_retries` times
+    pub async fn list_accounts(
+        &self,
+        fcm_id: &str,
+        ib_id: &str,
+    ) -> Result<Vec<RithmicAccount>, String> {
+        let accounts = self
+            .send_read(|tx| OrderPlantCommand::AccountList {
+                fcm_id: fcm_id.to_string(),
+                ib_id: ib_id.to_string(),
+                response_sender: tx,
+            })
+            .await?
+            .into_iter()
+            .filter_map(|response| match response.message {
+                RithmicMessage::ResponseAccountList(account) => Some(account),
+                _ => None,
+            })
+            .filter_map(|account| {
+                Some(RithmicAccount {
+                    fcm_id: account.fcm_id.unwrap_or_else(|| fcm_id.to_string()),
+                    ib_id: account.ib_id.unwrap_or_else(|| ib_id.to_string()),
+                    account_id: account.account_id?,
+                    account_name: account.account_name,
+                })
+            })
+            .collect();
+
+        Ok(accounts)
+    }
+
+    pub async fn select_account(&self, fcm_id: &str, ib_id: &str, account_id: &str) {
+        let command = OrderPlantCommand::SelectAccount {
+            fcm_id: fcm_id.to_string(),
+            ib_id: ib_id.to_string(),
+            account_id: account_id.to_string(),
+        };
+
+        let _ = self.sender.send(command).await;
+    }
+
+    /// Get the user and password from `provider` before each login, including the ones done by
+    /// `reconnect`, instead of the `RithmicConnectionInfo` credentials. None goes back to them.
+    pub async fn set_credentials_provider(&self, provider: Option<CredentialsProvider>) {
+        let command = OrderPlantCommand::SetCredentialsProvider { provider };
+
+        let _ = self.sender.send(command).await;
+    }
+
+    /// Tag orders, modifies and cancels as manual or automated unless their own `auto` flag is
+    /// set. Defaults to `OrderPlacement::Auto`.
+    pub async fn set_order_placement(&self, order_placement: OrderPlacement) {
+        let command = OrderPlantCommand::SetOrderPlacement { order_placement };
+
+        let _ = self.sender.send(command).await;
+    }
+
+    /// Open a new connection to the gateway, log in again and re-issue every
+    /// subscription that was active on the previous connection.
+    pub async fn reconnect(&self) -> Result<RithmicResponse, String> {
+        let _reconnecting = self.reconnect_lock.lock().await;
+
+        self.reconnect_locked().await
+    }
+
+    /// `reconnect`, for a caller holding the reconnect lock
+    async fn reconnect_locked(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<(), String>>();
+
+        let command = OrderPlantCommand::Reconnect {
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+        rx.await.map_err(not_connected)??;
+
+        let response = self.login().await?;
+
+        let (tx, rx) = oneshot::channel::<Vec<OrderPlantSubscription>>();
+
+        let command = OrderPlantCommand::ListSubscriptions {
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        for subscription in rx.await.map_err(not_connected)? {
+            event!(Level::INFO, "order_plant: resubscribing {:?}", subscription);
+
+            match subscription {
+                OrderPlantSubscription::OrderUpdates => {
+                    self.subscribe_order_updates().await?;
+                }
+                OrderPlantSubscription::BracketUpdates => {
+                    self.subscribe_bracket_updates().await?;
+                }
+                OrderPlantSubscription::AccountRmsUpdates => {
+                    self.subscribe_account_rms_updates().await?;
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
     pub async fn subscribe_order_updates(&self) -> Result<RithmicResponse, String> {
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
@@ -480,9 +1613,9 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn subscribe_bracket_updates(&self) -> Result<RithmicResponse, String> {
@@ -492,15 +1625,31 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Subscribe to the account's auto liquidation threshold updates. They are published on the
+    /// order plant's update channel as `AccountRmsUpdates`, see `RithmicAutoLiquidation::from_update`.
+    pub async fn subscribe_account_rms_updates(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = OrderPlantCommand::SubscribeAccountRmsUpdates {
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn place_bracket_order(
         &self,
         bracket_order: RithmicBracketOrder,
     ) -> Result<Vec<RithmicResponse>, String> {
+        self.check_trading()?;
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = OrderPlantCommand::PlaceBracketOrder {
@@ -508,12 +1657,184 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        rx.await.map_err(not_connected)?
+    }
+
+    /// Encode an order without sending it, returning the exact frame and request id that
+    /// `place_order` would send next
+    pub async fn encode_new_order(&self, order: RithmicOrder) -> Result<(Bytes, String), String> {
+        order.validate()?;
+
+        let (tx, rx) = oneshot::channel();
+
+        let command = OrderPlantCommand::EncodeOrder {
+            order,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        rx.await.map_err(not_connected)?
+    }
+
+    /// Place a single order and return its basket id
+    pub async fn place_order(&self, order: RithmicOrder) -> Result<String, OrderRejection> {
+        self.place_orders(vec![order]).await?.remove(0)
+    }
+
+    /// Place an order and wait for the order notification telling whether it reached the
+    /// exchange, up to `timeout`. A rejection by the gateway or the exchange is an
+    /// `OrderAck::Rejected`, an error means the outcome is unknown.
+    pub async fn place_order_confirmed(
+        &self,
+        order: RithmicOrder,
+        timeout: Duration,
+    ) -> Result<OrderAck, String> {
+        // Subscribed before placing the order so its notifications can't be missed
+        let mut receiver = self.subscription_sender.subscribe();
+
+        let basket_id = match self.place_order(order).await {
+            Ok(basket_id) => basket_id,
+            Err(rejection) if rejection.code.is_empty() => return Err(rejection.text),
+            Err(rejection) => {
+                return Ok(OrderAck::Rejected {
+                    basket_id: rejection.basket_id.clone(),
+                    reason: rejection.to_string(),
+                });
+            }
+        };
+
+        let ack_of = |response: &RithmicResponse| match &response.message {
+            RithmicMessage::RithmicOrderNotification(notification)
+                if notification.basket_id.as_deref() == Some(basket_id.as_str()) =>
+            {
+                OrderAck::from_notification(notification)
+            }
+            RithmicMessage::ExchangeOrderNotification(notification)
+                if notification.basket_id.as_deref() == Some(basket_id.as_str()) =>
+            {
+                OrderAck::from_exchange_notification(notification)
+            }
+            _ => None,
+        };
+
+        let is_ack = |response: &RithmicResponse| ack_of(response).is_some();
+
+        let response = wait_for_update(&mut receiver, is_ack, timeout)
+            .await
+            .map_err(|e| format!("order {}: not confirmed, {}", basket_id, e))?;
+
+        ack_of(&response).ok_or_else(|| format!("order {}: not confirmed", basket_id))
+    }
+
+    /// Place several orders back-to-back, without other requests being sent in between.
+    /// Returns the basket id or the error of each order, in submission order, so a partial
+    /// failure shows which legs went through. Orders are never re-sent after a connection loss.
+    pub async fn place_orders(
+        &self,
+        orders: Vec<RithmicOrder>,
+    ) -> Result<Vec<Result<String, OrderRejection>>, String> {
+        self.check_trading()?;
+
+        for order in &orders {
+            order.validate()?;
+        }
+
+        let (tx, rx) = oneshot::channel();
+
+        let command = OrderPlantCommand::PlaceOrders {
+            orders,
+            response_sender: tx,
+        };
 
-        rx.await.unwrap()
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        let mut basket_ids = vec![];
+
+        for order_rx in rx.await.map_err(not_connected)?? {
+            let basket_id = order_rx
+                .await
+                .unwrap_or_else(|e| Err(not_connected(e)))
+                .map_err(OrderRejection::from)
+                .and_then(|responses| {
+                    let order = responses
+                        .into_iter()
+                        .find_map(|response| match response.message {
+                            RithmicMessage::ResponseNewOrder(order) => Some(order),
+                            _ => None,
+                        })
+                        .ok_or_else(|| "no new order response".to_string())?;
+
+                    if let Some(rejection) = OrderRejection::from_response(&order) {
+                        return Err(rejection);
+                    }
+
+                    order
+                        .basket_id
+                        .ok_or_else(|| "new order response has no basket id".to_string().into())
+                });
+
+            if let Err(e) = &basket_id {
+                event!(Level::ERROR, "order_plant: order rejected {}", e);
+            }
+
+            basket_ids.push(basket_id);
+        }
+
+        Ok(basket_ids)
+    }
+
+    /// Queue an order without waiting, fails right away when the plant's command channel is
+    /// full so the caller can drop or retry it. The outcome is only known from the order
+    /// updates, e.g. with `resolve_by_tag` on the order's `localid`.
+    pub fn try_place_order(&self, order: RithmicOrder) -> Result<(), String> {
+        self.check_trading()?;
+        order.validate()?;
+
+        match self.sender.try_send(OrderPlantCommand::PlaceDetachedOrder { order }) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err("order plant command channel is full".to_string()),
+            Err(TrySendError::Closed(_)) => Err("order plant is closed".to_string()),
+        }
+    }
+
+    /// Send `order` once the order with `basket_id` is completely filled. The chained order is
+    /// dropped if that order is cancelled or rejected first. Chains are kept by the plant, they
+    /// are lost if it stops.
+    pub async fn on_fill(&self, basket_id: &str, order: RithmicOrder) -> Result<(), String> {
+        self.check_trading()?;
+        order.validate()?;
+
+        let command = OrderPlantCommand::ChainOrder {
+            trigger_basket_id: basket_id.to_string(),
+            order,
+        };
+
+        self.sender
+            .send(command)
+            .await
+            .map_err(|_| "order plant is closed".to_string())
+    }
+
+    /// Send an order without waiting for the gateway's response, only for room in the plant's
+    /// command channel. Like `try_place_order`, the outcome is only known from the order updates.
+    pub async fn place_order_fire_and_forget(&self, order: RithmicOrder) -> Result<(), String> {
+        self.check_trading()?;
+        order.validate()?;
+
+        self.sender
+            .send(OrderPlantCommand::PlaceDetachedOrder { order })
+            .await
+            .map_err(|_| "order plant is closed".to_string())
     }
 
+    /// Can change the order type, e.g. limit to market. The prices must match the new type,
+    /// see `RithmicModifyOrder::validate`
     pub async fn modify_order(&self, order: RithmicModifyOrder) -> Result<RithmicResponse, String> {
+        order.validate()?;
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = OrderPlantCommand::ModifyOrder {
@@ -521,9 +1842,9 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn cancel_order(&self, order: RithmicCancelOrder) -> Result<RithmicResponse, String> {
@@ -531,12 +1852,129 @@ impl RithmicOrderPlantHandle {
 
         let command = OrderPlantCommand::CancelOrder {
             order_id: order.id,
+            auto: order.auto,
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Cancel an order by its `localid`, which is sent as its user tag. The cancel can be sent
+    /// right after placing the order: until the server assigns the order its basket id, it
+    /// waits in the plant and goes out as soon as the id arrives. It fails if the order is
+    /// rejected, the connection is lost, or no response arrives within `timeout`, e.g. for a
+    /// tag never placed. A second cancel of a tag still waiting for its basket id fails.
+    pub async fn cancel_order_by_tag(
+        &self,
+        user_tag: &str,
+        auto: Option<bool>,
+        timeout: Duration,
+    ) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = OrderPlantCommand::CancelOrderByTag {
+            user_tag: user_tag.to_string(),
+            auto,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        let mut responses = tokio::time::timeout(timeout, rx)
+            .await
+            .map_err(|_| format!("order {}: cancel not answered after {:?}", user_tag, timeout))?
+            .map_err(not_connected)??;
+
+        Ok(responses.remove(0))
+    }
+
+    /// Exchanges the user is entitled to. Idempotent, re-sent after a reconnect up to
+    /// `read_retries` times
+    pub async fn entitled_exchanges(&self) -> Result<Vec<String>, String> {
+        let exchanges = self
+            .send_read(|tx| OrderPlantCommand::ExchangePermissions {
+                response_sender: tx,
+            })
+            .await?
+            .into_iter()
+            .filter_map(|response| match response.message {
+                RithmicMessage::ResponseListExchangePermissions(permission) => Some(permission),
+                _ => None,
+            })
+            .filter(|permission| permission.entitlement_flag() == EntitlementFlag::Enabled)
+            .filter_map(|permission| permission.exchange)
+            .collect();
+
+        Ok(exchanges)
+    }
+
+    /// Trade routes of the user, sorted by exchange then route so the result doesn't depend on
+    /// the order the server sent them in. See `RithmicTradeRoute::preferred` to pick one per
+    /// exchange. Idempotent, re-sent after a reconnect up to `read_retries` times.
+    pub async fn trade_routes(&self) -> Result<Vec<RithmicTradeRoute>, String> {
+        let mut routes: Vec<RithmicTradeRoute> = self
+            .send_read(|tx| OrderPlantCommand::TradeRoutes {
+                response_sender: tx,
+            })
+            .await?
+            .iter()
+            .filter_map(|response| match &response.message {
+                RithmicMessage::ResponseTradeRoutes(route) => RithmicTradeRoute::from_response(route),
+                _ => None,
+            })
+            .collect();
+
+        routes.sort_by(|a, b| (&a.exchange, &a.trade_route).cmp(&(&b.exchange, &b.trade_route)));
+
+        Ok(routes)
+    }
+
+    /// Check the user is entitled to `exchange` before subscribing to its market data,
+    /// the ticker plant otherwise accepts the subscription and sends nothing
+    pub async fn is_entitled(&self, exchange: &str) -> Result<bool, String> {
+        Ok(self
+            .entitled_exchanges()
+            .await?
+            .iter()
+            .any(|entitled| entitled == exchange))
+    }
+
+    /// Cancel every working order of the active account. The response only acknowledges the
+    /// request, it carries no count: each cancellation is reported by its own order notification.
+    pub async fn cancel_all_orders(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = OrderPlantCommand::CancelAllOrders {
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Cancel only the target or the stop of a bracket placed with `place_bracket_order`.
+    /// The child orders are learnt from the order notifications, so `subscribe_order_updates`
+    /// must be active. If the server cancels the sibling as well, its completion notification
+    /// comes through the update stream like any other.
+    pub async fn cancel_bracket_leg(
+        &self,
+        parent_basket_id: &str,
+        leg: BracketLeg,
+    ) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = OrderPlantCommand::CancelBracketLeg {
+            parent_basket_id: parent_basket_id.to_string(),
+            leg,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn adjust_profit(&self, id: &str, ticks: i32) -> Result<RithmicResponse, String> {
@@ -548,9 +1986,9 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn adjust_stop(&self, id: &str, ticks: i32) -> Result<RithmicResponse, String> {
@@ -562,20 +2000,426 @@ impl RithmicOrderPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
+    /// Idempotent, re-sent after a reconnect up to `read_retries` times
     pub async fn show_orders(&self) -> Result<RithmicResponse, String> {
-        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+        let mut responses = self
+            .send_read(|tx| OrderPlantCommand::ShowOrders {
+                response_sender: tx,
+            })
+            .await?;
+
+        Ok(responses.remove(0))
+    }
+
+    /// Latest order notification of each open order, from the notifications the server replays
+    /// in answer to show_orders. Idempotent, re-sent after a reconnect up to `read_retries` times.
+    pub async fn open_orders(&self) -> Result<Vec<RithmicOrderNotification>, String> {
+        let mut updates = self.subscription_sender.subscribe();
+
+        self.show_orders().await?;
+
+        let mut orders: Vec<RithmicOrderNotification> = vec![];
+
+        for update in drain_updates(&mut updates)? {
+            let RithmicMessage::RithmicOrderNotification(notification) = update.message else {
+                continue;
+            };
+
+            match orders.iter().position(|o| o.basket_id == notification.basket_id) {
+                Some(i) => orders[i] = notification,
+                None => orders.push(notification),
+            }
+        }
+
+        Ok(orders)
+    }
+
+    /// Cancel the stop orders still waiting for their trigger price, only those on `symbol` if
+    /// given. Stops that already triggered are working at the exchange and are left alone.
+    /// Returns the basket ids of the cancelled stops.
+    pub async fn cancel_all_stops(&self, symbol: Option<&str>) -> Result<Vec<String>, String> {
+        let resting_stops: Vec<String> = self
+            .open_orders()
+            .await?
+            .into_iter()
+            .filter(|order| {
+                matches!(order.price_type(), PriceType::StopLimit | PriceType::StopMarket)
+                    && order.notify_type() == NotifyType::TriggerPending
+                    && symbol.is_none_or(|symbol| order.symbol.as_deref() == Some(symbol))
+            })
+            .filter_map(|order| order.basket_id)
+            .collect();
+
+        self.cancel_baskets(resting_stops, "stops").await
+    }
+
+    /// Cancel the open orders whose `user_tag`, the `localid` they were placed with, starts with
+    /// `prefix`, e.g. all the orders of one strategy tagging its orders "stratA-". Returns the
+    /// basket ids of the cancelled orders.
+    pub async fn cancel_orders_with_tag_prefix(&self, prefix: &str) -> Result<Vec<String>, String> {
+        let tagged_orders: Vec<String> = self
+            .open_orders()
+            .await?
+            .into_iter()
+            .filter(|order| {
+                order
+                    .user_tag
+                    .as_deref()
+                    .is_some_and(|user_tag| user_tag.starts_with(prefix))
+            })
+            .filter_map(|order| order.basket_id)
+            .collect();
+
+        self.cancel_baskets(tagged_orders, "orders").await
+    }
+
+    /// Cancel each basket, the error lists the ones that couldn't be cancelled
+    async fn cancel_baskets(&self, basket_ids: Vec<String>, what: &str) -> Result<Vec<String>, String> {
+        let mut failures = vec![];
+
+        for basket_id in &basket_ids {
+            let result = self
+                .cancel_order(RithmicCancelOrder {
+                    id: basket_id.clone(),
+                    auto: None,
+                })
+                .await;
+
+            if let Err(e) = result {
+                failures.push(format!("{}: {}", basket_id, e));
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(basket_ids)
+        } else {
+            Err(format!("failed to cancel {} {}", what, failures.join(", ")))
+        }
+    }
+
+    /// Fills of the account between `start_ssboe` and `finish_ssboe` (seconds since epoch),
+    /// collected from the execution notifications the server replays before answering.
+    /// Idempotent, re-sent after a reconnect up to `read_retries` times.
+    pub async fn replay_executions(
+        &self,
+        start_ssboe: i32,
+        finish_ssboe: i32,
+    ) -> Result<Vec<RithmicFill>, String> {
+        let mut updates = self.subscription_sender.subscribe();
+
+        self.send_read(|tx| OrderPlantCommand::ReplayExecutions {
+            start_index: start_ssboe,
+            finish_index: finish_ssboe,
+            response_sender: tx,
+        })
+        .await?;
+
+        let mut fills = vec![];
+
+        for update in drain_updates(&mut updates)? {
+            if let RithmicMessage::ExchangeOrderNotification(notification) = &update.message {
+                fills.extend(RithmicFill::from_notification(notification));
+            }
+        }
+
+        Ok(fills)
+    }
+
+    /// Rebuild the net position of each symbol from the executions between `start_ssboe` and
+    /// `finish_ssboe`, e.g. after a restart. Positions opened before `start_ssboe` are not
+    /// included, compare with the pnl plant's position snapshot to catch those.
+    pub async fn reconcile_positions_from_executions(
+        &self,
+        start_ssboe: i32,
+        finish_ssboe: i32,
+    ) -> Result<HashMap<String, RithmicNetPosition>, String> {
+        let mut positions: HashMap<String, RithmicNetPosition> = HashMap::new();
+
+        for fill in self.replay_executions(start_ssboe, finish_ssboe).await? {
+            positions
+                .entry(fill.symbol.clone())
+                .or_insert_with(|| RithmicNetPosition {
+                    symbol: fill.symbol.clone(),
+                    exchange: fill.exchange.clone(),
+                    ..RithmicNetPosition::default()
+                })
+                .apply_fill(&fill);
+        }
+
+        Ok(positions)
+    }
+
+    /// Basket id the server assigned to the order sent with `request_id`,
+    /// e.g. the id returned by `encode_new_order`
+    pub async fn resolve_basket_id(&self, request_id: &str) -> Option<String> {
+        let (tx, rx) = oneshot::channel();
+
+        let command = OrderPlantCommand::ResolveBasketId {
+            request_id: request_id.to_string(),
+            response_sender: tx,
+        };
+
+        let _ = self.sender.send(command).await;
+
+        rx.await.ok()?
+    }
+
+    /// Basket id of the order placed with `user_tag` as its local id, also known for orders
+    /// placed by other sessions once their notifications are received
+    pub async fn resolve_by_tag(&self, user_tag: &str) -> Option<String> {
+        let (tx, rx) = oneshot::channel();
+
+        let command = OrderPlantCommand::ResolveByTag {
+            user_tag: user_tag.to_string(),
+            response_sender: tx,
+        };
+
+        let _ = self.sender.send(command).await;
+
+        rx.await.ok()?
+    }
+
+    /// Time from sending the order to the first exchange notification accepting it, None until
+    /// then or for an order not placed by this session
+    pub async fn order_latency(&self, basket_id: &str) -> Option<Duration> {
+        let (tx, rx) = oneshot::channel();
 
-        let command = OrderPlantCommand::ShowOrders {
+        let command = OrderPlantCommand::OrderLatency {
+            basket_id: basket_id.to_string(),
             response_sender: tx,
         };
 
         let _ = self.sender.send(command).await;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        rx.await.ok()?
+    }
+
+    /// Latency stats of the open orders and the last `COMPLETED_ORDERS_KEPT` completed ones,
+    /// see `order_latency`
+    pub async fn order_latency_stats(&self) -> Option<OrderLatencyStats> {
+        let (tx, rx) = oneshot::channel();
+
+        let _ = self
+            .sender
+            .send(OrderPlantCommand::OrderLatencyStats { response_sender: tx })
+            .await;
+
+        rx.await.ok()?
+    }
+
+    /// Today's orders of the account, or only `basket_id`, with their final state and fills.
+    ///
+    /// The server replays the order notifications on the update stream before answering, they
+    /// are collected from a receiver opened before the request. Idempotent, re-sent after a
+    /// reconnect up to `read_retries` times.
+    pub async fn order_history(
+        &self,
+        basket_id: Option<&str>,
+    ) -> Result<Vec<RithmicHistoricalOrder>, String> {
+        let mut updates = self.subscription_sender.subscribe();
+
+        self.send_read(|tx| OrderPlantCommand::ShowOrderHistory {
+            basket_id: basket_id.map(|b| b.to_string()),
+            response_sender: tx,
+        })
+        .await?;
+
+        collect_historical_orders(&mut updates, basket_id)
+    }
+
+    /// Dates with an order history for the account, YYYYMMDD, oldest first. Idempotent,
+    /// re-sent after a reconnect up to `read_retries` times.
+    pub async fn order_history_dates(&self) -> Result<Vec<String>, String> {
+        let responses = self
+            .send_read(|tx| OrderPlantCommand::ShowOrderHistoryDates {
+                response_sender: tx,
+            })
+            .await?;
+
+        let mut dates: Vec<String> = responses
+            .into_iter()
+            .flat_map(|response| match response.message {
+                RithmicMessage::ResponseShowOrderHistoryDates(resp) => resp.date,
+                _ => vec![],
+            })
+            .collect();
+
+        dates.sort();
+        dates.dedup();
+
+        Ok(dates)
+    }
+
+    /// Orders of the account on `date`, YYYYMMDD, with their final state and fills. Like
+    /// `order_history`, they are rebuilt from the notifications replayed before the response.
+    pub async fn order_history_summary(
+        &self,
+        date: &str,
+    ) -> Result<Vec<RithmicHistoricalOrder>, String> {
+        let mut updates = self.subscription_sender.subscribe();
+
+        self.send_read(|tx| OrderPlantCommand::ShowOrderHistorySummary {
+            date: date.to_string(),
+            response_sender: tx,
+        })
+        .await?;
+
+        collect_historical_orders(&mut updates, None)
+    }
+
+    /// Orders of the account from `start` to `end` included, both YYYYMMDD, oldest date first.
+    /// Only the dates returned by `order_history_dates` are fetched, a day without history,
+    /// e.g. a weekend, is skipped rather than failing the range.
+    pub async fn order_history_range(
+        &self,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<RithmicHistoricalOrder>, String> {
+        if start > end {
+            return Err(format!("order history range starts after it ends: {} > {}", start, end));
+        }
+
+        let mut orders = vec![];
+
+        for date in self.order_history_dates().await? {
+            if (start..=end).contains(&date.as_str()) {
+                orders.extend(self.order_history_summary(&date).await?);
+            }
+        }
+
+        Ok(orders)
+    }
+
+    /// Every history date with its orders, oldest first. The dates are fetched up front, the
+    /// orders of a date only when the stream is polled for it, so a long history is never all
+    /// in memory at once.
+    pub async fn iter_order_history(
+        &self,
+    ) -> Result<impl Stream<Item = Result<(String, Vec<RithmicHistoricalOrder>), String>>, String>
+    {
+        let dates = self.order_history_dates().await?;
+        let handle = self.clone();
+
+        Ok(stream::iter(dates).then(move |date| {
+            let handle = handle.clone();
+
+            async move {
+                let orders = handle.order_history_summary(&date).await?;
+
+                Ok((date, orders))
+            }
+        }))
+    }
+
+    /// Send an idempotent read request. If the connection is lost before the response arrives
+    /// the plant is reconnected and the request re-sent, up to `read_retries` times.
+    /// Order requests must not go through here, re-sending them could duplicate orders.
+    async fn send_read<F>(&self, command: F) -> Result<Vec<RithmicResponse>, String>
+    where
+        F: Fn(oneshot::Sender<Result<Vec<RithmicResponse>, String>>) -> OrderPlantCommand,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let reconnect_count = self.connection_monitor.stats().reconnect_count;
+
+            let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+            self.sender.send(command(tx)).await.map_err(not_connected)?;
+
+            match rx.await.map_err(not_connected)? {
+                Err(e) if e == CONNECTION_LOST && attempt < self.read_retries => {
+                    attempt += 1;
+
+                    event!(
+                        Level::WARN,
+                        "order_plant: connection lost during read, retry {}/{}",
+                        attempt,
+                        self.read_retries
+                    );
+
+                    // Reads failed by the same loss wait for the first of them to reconnect,
+                    // then retry on its connection
+                    let _reconnecting = self.reconnect_lock.lock().await;
+
+                    if self.connection_monitor.stats().reconnect_count == reconnect_count {
+                        self.reconnect_locked().await?;
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Orders rebuilt from the notifications the server replays before answering a history
+/// request. The plant forwards them before handling the response, so they are all in
+/// `updates` by the time it arrives.
+fn collect_historical_orders(
+    updates: &mut broadcast::Receiver<RithmicResponse>,
+    basket_id: Option<&str>,
+) -> Result<Vec<RithmicHistoricalOrder>, String> {
+    let mut orders: Vec<RithmicHistoricalOrder> = vec![];
+
+    for update in drain_updates(updates)? {
+        let order_basket_id = match &update.message {
+            RithmicMessage::RithmicOrderNotification(n) => n.basket_id.clone(),
+            RithmicMessage::ExchangeOrderNotification(n) => n.basket_id.clone(),
+            _ => None,
+        };
+
+        let Some(order_basket_id) = order_basket_id else {
+            continue;
+        };
+
+        if basket_id.is_some_and(|b| b != order_basket_id) {
+            continue;
+        }
+
+        let order = match orders.iter().position(|o| o.basket_id == order_basket_id) {
+            Some(i) => &mut orders[i],
+            None => {
+                orders.push(RithmicHistoricalOrder {
+                    basket_id: order_basket_id,
+                    ..RithmicHistoricalOrder::default()
+                });
+
+                orders.last_mut().unwrap()
+            }
+        };
+
+        match &update.message {
+            RithmicMessage::RithmicOrderNotification(n) => order.apply_order_notification(n),
+            RithmicMessage::ExchangeOrderNotification(n) => order.apply_exchange_notification(n),
+            _ => {}
+        }
+    }
+
+    Ok(orders)
+}
+
+/// The plant's actor stopped, e.g. its connection was closed or failed
+fn not_connected<E>(_: E) -> String {
+    plant_not_connected("order")
+}
+
+impl Clone for RithmicOrderPlantHandle {
+    fn clone(&self) -> Self {
+        RithmicOrderPlantHandle {
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
+            read_retries: self.read_retries,
+            reconnect_lock: self.reconnect_lock.clone(),
+            sender: self.sender.clone(),
+            server_clock: self.server_clock.clone(),
+            subscription_sender: self.subscription_sender.clone(),
+            subscription_receiver: self.subscription_sender.subscribe(),
+            trading_halted: self.trading_halted.clone(),
+        }
     }
 }
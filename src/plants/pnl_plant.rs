@@ -1,15 +1,26 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
 use async_trait::async_trait;
 use tracing::{event, Level};
 
 use crate::{
     api::{
-        RithmicConnectionInfo,
+        RithmicConnectionInfo, UnheardUpdates,
+        frame_capture::{CapturedFrame, FrameCapture},
         receiver_api::{RithmicReceiverApi, RithmicResponse},
         sender_api::RithmicSenderApi,
     },
+    plants::order_plant::RithmicOrderPlantHandle,
     request_handler::{RithmicRequest, RithmicRequestHandler},
-    rti::{request_login::SysInfraType, request_pn_l_position_updates},
-    ws::{get_heartbeat_interval, PlantActor, RithmicStream, connect},
+    rti::{
+        AccountPnLPositionUpdate, InstrumentPnLPositionUpdate, RithmicOrderNotification,
+        messages::RithmicMessage, request_login::SysInfraType, request_pn_l_position_updates,
+    },
+    ws::{ConnectionMonitor, ConnectionStats, IdleTimer, UnsubscribeResponses, await_unsubscribes, drain_updates, get_heartbeat_interval, plant_not_connected, PlantActor, RithmicStream, connect_with_failover},
 };
 
 use futures_util::{
@@ -19,8 +30,11 @@ use futures_util::{
 
 use tokio::{
     net::TcpStream,
-    sync::{broadcast::Sender, oneshot},
-    time::Interval,
+    sync::{
+        broadcast::{error::RecvError, Sender},
+        mpsc, oneshot,
+    },
+    time::{self, Interval},
 };
 
 use tokio_tungstenite::{
@@ -46,32 +60,70 @@ pub enum PnlPlantCommand {
     SubscribePnlUpdates {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    SelectAccount {
+        fcm_id: String,
+        ib_id: String,
+        account_id: String,
+    },
+    UnsubscribeAll {
+        response_sender: oneshot::Sender<UnsubscribeResponses>,
+    },
 }
 
+/// Account updates buffered for a consumer of `account_pnl_updates`
+const ACCOUNT_PNL_BUFFER_SIZE: usize = 1024;
+
+/// Account balance samples, oldest first
+type EquityCurve = Arc<Mutex<VecDeque<(SystemTime, f64)>>>;
+
 pub struct RithmicPnlPlant {
     pub connection_handle: tokio::task::JoinHandle<()>,
+    connection_monitor: ConnectionMonitor,
+    equity_curve: EquityCurve,
+    frame_capture: Option<FrameCapture>,
     sender: tokio::sync::mpsc::Sender<PnlPlantCommand>,
     subscription_sender: Sender<RithmicResponse>,
 }
 
 impl RithmicPnlPlant {
+    /// Panics when the gateway can't be reached, see `try_new`
     pub async fn new(conn_info: &RithmicConnectionInfo) -> RithmicPnlPlant {
+        Self::try_new(conn_info).await.unwrap()
+    }
+
+    /// Connect to the gateway, failing instead of panicking so an application can carry on
+    /// without this plant
+    pub async fn try_new(conn_info: &RithmicConnectionInfo) -> Result<RithmicPnlPlant, String> {
         let (req_tx, req_rx) = tokio::sync::mpsc::channel::<PnlPlantCommand>(32);
-        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(1024);
+        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(conn_info.update_buffer_size.max(1));
 
-        let mut pnl_plant = PnlPlant::new(req_rx, sub_tx.clone(), conn_info)
-            .await
-            .unwrap();
+        let frame_capture = FrameCapture::from_capacity("pnl_plant", conn_info.capture_raw_frames);
+
+        let equity_curve = EquityCurve::default();
+
+        let mut pnl_plant = PnlPlant::new(
+            req_rx,
+            sub_tx.clone(),
+            conn_info,
+            frame_capture.clone(),
+            equity_curve.clone(),
+        )
+        .await?;
+
+        let connection_monitor = pnl_plant.connection_monitor.clone();
 
         let connection_handle = tokio::spawn(async move {
             pnl_plant.run().await;
         });
 
-        RithmicPnlPlant {
+        Ok(RithmicPnlPlant {
             connection_handle,
+            connection_monitor,
+            equity_curve,
+            frame_capture,
             sender: req_tx,
             subscription_sender: sub_tx,
-        }
+        })
     }
 }
 
@@ -80,7 +132,11 @@ impl RithmicStream for RithmicPnlPlant {
 
     fn get_handle(&self) -> Self::Handle {
         RithmicPnlPlantHandle {
+            equity_curve: self.equity_curve.clone(),
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
             sender: self.sender.clone(),
+            subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
         }
     }
@@ -88,9 +144,17 @@ impl RithmicStream for RithmicPnlPlant {
 
 #[derive(Debug)]
 pub struct PnlPlant {
+    /// Last account balance received, sampled into `equity_curve`
+    account_balance: Option<f64>,
     config: RithmicConnectionInfo,
+    connection_monitor: ConnectionMonitor,
+    equity_curve: EquityCurve,
+    equity_interval: Interval,
+    idle_timer: IdleTimer,
     interval: Interval,
     logged_in: bool,
+    /// Whether `SubscribePnlUpdates` was sent, unsubscribed on disconnect
+    pnl_updates_subscribed: bool,
     request_handler: RithmicRequestHandler,
     request_receiver: tokio::sync::mpsc::Receiver<PnlPlantCommand>,
     rithmic_reader: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
@@ -108,22 +172,41 @@ impl PnlPlant {
         request_receiver: tokio::sync::mpsc::Receiver<PnlPlantCommand>,
         subscription_sender: Sender<RithmicResponse>,
         conn_info: &RithmicConnectionInfo,
-    ) -> Result<PnlPlant, ()> {
-        let config = conn_info.clone();
+        frame_capture: Option<FrameCapture>,
+        equity_curve: EquityCurve,
+    ) -> Result<PnlPlant, String> {
+        let mut config = conn_info.clone();
 
-        let ws_stream = connect(&config.url).await.unwrap();
+        let (ws_stream, url) = connect_with_failover(&config.gateway_urls(), &config.ws_headers)
+            .await
+            .map_err(|e| format!("pnl_plant: {}", e))?;
+        // Reconnect to the gateway that worked first
+        config.url = url;
         let (rithmic_sender, rithmic_reader) = ws_stream.split();
-        let rithmic_sender_api = RithmicSenderApi::new(&config);
+        let mut rithmic_sender_api = RithmicSenderApi::new(&config);
+        rithmic_sender_api.set_frame_capture(frame_capture.clone());
+
         let rithmic_receiver_api = RithmicReceiverApi {
             source: "pnl_plant".to_string(),
+            frame_capture,
+            template_version: config.template_version.clone(),
         };
 
+        let idle_timer = IdleTimer::new(config.auto_logout_after_secs);
         let interval = get_heartbeat_interval();
+        // Only polled when the sampling is enabled
+        let equity_interval = time::interval(Duration::from_secs(config.equity_sample_interval_secs.max(1)));
 
         Ok(PnlPlant {
+            account_balance: None,
             config,
+            connection_monitor: ConnectionMonitor::default(),
+            equity_curve,
+            equity_interval,
+            idle_timer,
             interval,
             logged_in: false,
+            pnl_updates_subscribed: false,
             request_handler: RithmicRequestHandler::new(),
             request_receiver,
             rithmic_reader,
@@ -133,6 +216,45 @@ impl PnlPlant {
             subscription_sender,
         })
     }
+
+    /// Log out and close the connection once no request has been sent for `auto_logout_after_secs`
+    async fn auto_logout(&mut self) {
+        event!(
+            Level::WARN,
+            "pnl_plant: no request for {}s, logging out",
+            self.config.auto_logout_after_secs
+        );
+
+        self.logged_in = false;
+
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        self.handle_command(PnlPlantCommand::Logout { response_sender: tx }).await;
+        self.handle_command(PnlPlantCommand::Close).await;
+
+        tokio::spawn(async move {
+            if let Ok(Err(e)) = rx.await {
+                event!(Level::ERROR, "pnl_plant: auto logout failed: {}", e);
+            }
+        });
+    }
+
+    /// Record the last account balance received, nothing until the first pnl update
+    fn sample_equity(&mut self) {
+        let Some(balance) = self.account_balance else {
+            return;
+        };
+
+        let mut equity_curve = self.equity_curve.lock().unwrap();
+
+        if equity_curve.len() >= self.config.equity_curve_size {
+            equity_curve.pop_front();
+        }
+
+        if self.config.equity_curve_size > 0 {
+            equity_curve.push_back((SystemTime::now(), balance));
+        }
+    }
 }
 
 #[async_trait]
@@ -143,9 +265,18 @@ impl PlantActor for PnlPlant {
         loop {
             tokio::select! {
                 _ = self.interval.tick() => {
-                    self.handle_command(PnlPlantCommand::SendHeartbeat {}).await;
+                    if self.logged_in && self.idle_timer.expired() {
+                        self.auto_logout().await;
+                    } else {
+                        self.handle_command(PnlPlantCommand::SendHeartbeat {}).await;
+                    }
+                }
+                _ = self.equity_interval.tick(), if self.config.equity_sample_interval_secs > 0 => {
+                    self.sample_equity();
                 }
                 Some(message) = self.request_receiver.recv() => {
+                    self.idle_timer.touch();
+
                     self.handle_command(message).await;
                 }
                 Some(message) = self.rithmic_reader.next() => {
@@ -173,19 +304,27 @@ impl PlantActor for PnlPlant {
             }
             Ok(Message::Binary(data)) => match self.rithmic_receiver_api.buf_to_message(data) {
                 Ok(response) => {
-                    if response.is_update {
-                        match self.subscription_sender.send(response) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                event!(Level::ERROR, "failed to send response {:?}", e);
-                            }
-                        };
-                    } else {
-                        self.request_handler.handle_response(response);
+                    if let RithmicMessage::AccountPnLPositionUpdate(update) = &response.message
+                        && let Some(balance) = update
+                            .account_balance
+                            .as_deref()
+                            .and_then(|balance| balance.parse().ok())
+                    {
+                        self.account_balance = Some(balance);
+                    }
+
+                    if self.request_handler.dispatch(response, &self.subscription_sender)
+                        && self.config.unheard_updates == UnheardUpdates::Close
+                    {
+                        event!(Level::WARN, "pnl_plant: no update receiver left, closing");
+
+                        self.handle_command(PnlPlantCommand::Close).await;
                     }
                 }
                 Err(err) => {
                     event!(Level::ERROR, "received an error message {:?}", err);
+
+                    self.connection_monitor.error(&err);
                 }
             },
             Err(Error::ConnectionClosed) => {
@@ -194,6 +333,10 @@ impl PlantActor for PnlPlant {
             }
             _ => {
                 event!(Level::WARN, "Unhandled message: {:?}", message);
+
+                if let Err(e) = &message {
+                    self.connection_monitor.error(e);
+                }
             }
         }
 
@@ -253,10 +396,17 @@ impl PlantActor for PnlPlant {
                     .await;
             }
             PnlPlantCommand::SubscribePnlUpdates { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
                 let (subscribe_buf, id) = self.rithmic_sender_api.request_pnl_position_updates(
                     request_pn_l_position_updates::Request::Subscribe,
                 );
 
+                self.pnl_updates_subscribed = true;
+
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
@@ -268,6 +418,11 @@ impl PlantActor for PnlPlant {
                     .unwrap();
             }
             PnlPlantCommand::PnlPositionSnapshots { response_sender } => {
+                if let Err(e) = self.rithmic_sender_api.require_account() {
+                    let _ = response_sender.send(Err(e));
+                    return;
+                }
+
                 let (snapshot_buf, id) = self.rithmic_sender_api.request_pnl_position_snapshot();
 
                 self.request_handler.register_request(RithmicRequest {
@@ -280,16 +435,73 @@ impl PlantActor for PnlPlant {
                     .await
                     .unwrap();
             }
+            PnlPlantCommand::SelectAccount {
+                fcm_id,
+                ib_id,
+                account_id,
+            } => {
+                self.rithmic_sender_api.set_account(&fcm_id, &ib_id, &account_id);
+            }
+            PnlPlantCommand::UnsubscribeAll { response_sender } => {
+                let mut responses = Vec::new();
+
+                if self.pnl_updates_subscribed {
+                    let (unsubscribe_buf, id) = self.rithmic_sender_api.request_pnl_position_updates(
+                        request_pn_l_position_updates::Request::Unsubscribe,
+                    );
+
+                    let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                    self.request_handler.register_request(RithmicRequest {
+                        request_id: id,
+                        responder: tx,
+                    });
+
+                    let _ = self.rithmic_sender.send(Message::Binary(unsubscribe_buf)).await;
+
+                    self.pnl_updates_subscribed = false;
+                    responses.push(rx);
+                }
+
+                let _ = response_sender.send(responses);
+            }
         }
     }
 }
 
+/// Cloning a handle is cheap: clones share the plant's command channel and each gets its own
+/// update receiver. Handles are `Send + Sync`, clone one into each task issuing requests.
 pub struct RithmicPnlPlantHandle {
+    equity_curve: EquityCurve,
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
     sender: tokio::sync::mpsc::Sender<PnlPlantCommand>,
+    subscription_sender: Sender<RithmicResponse>,
     pub subscription_receiver: tokio::sync::broadcast::Receiver<RithmicResponse>,
 }
 
 impl RithmicPnlPlantHandle {
+    /// Last raw frames sent and received by the plant, empty unless
+    /// `RithmicConnectionInfo::capture_raw_frames` is set
+    pub fn recent_frames(&self) -> Vec<CapturedFrame> {
+        self.frame_capture
+            .as_ref()
+            .map(|frame_capture| frame_capture.recent_frames())
+            .unwrap_or_default()
+    }
+
+    /// Since when the plant has been connected, how many times it reconnected and its last
+    /// connection or server error
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_monitor.stats()
+    }
+
+    /// Account balance sampled every `RithmicConnectionInfo::equity_sample_interval_secs`,
+    /// oldest first. Samples start with the first pnl update, see `subscribe_pnl_updates`.
+    pub fn equity_curve(&self) -> Vec<(SystemTime, f64)> {
+        self.equity_curve.lock().unwrap().iter().copied().collect()
+    }
+
     pub async fn login(&self) -> Result<RithmicResponse, String> {
         event!(Level::INFO, "pnl_plant: logging in");
 
@@ -299,36 +511,60 @@ impl RithmicPnlPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let response = rx.await.unwrap().unwrap().remove(0);
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        if response.error.is_none() {
-            let _ = self.sender.send(PnlPlantCommand::SetLogin).await;
+        match rx.await.map_err(not_connected)? {
+            Ok(mut responses) => {
+                let _ = self.sender.send(PnlPlantCommand::SetLogin).await;
 
-            event!(Level::INFO, "pnl_plant: logged in");
+                event!(Level::INFO, "pnl_plant: logged in");
 
-            Ok(response)
-        } else {
-            event!(Level::ERROR, "pnl_plant: login failed {:?}", response.error);
+                Ok(responses.remove(0))
+            }
+            Err(e) => {
+                event!(Level::ERROR, "pnl_plant: login failed {}", e);
 
-            Err(response.error.unwrap())
+                Err(e)
+            }
         }
     }
 
+    /// Unsubscribe the pnl updates, then log out and close the connection
     pub async fn disconnect(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel();
+
+        self.sender
+            .send(PnlPlantCommand::UnsubscribeAll { response_sender: tx })
+            .await
+            .map_err(not_connected)?;
+
+        if let Ok(responses) = rx.await {
+            await_unsubscribes("pnl_plant", responses).await;
+        }
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = PnlPlantCommand::Logout {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let mut r = rx.await.unwrap().unwrap();
+        self.sender.send(command).await.map_err(not_connected)?;
+        let mut r = rx.await.map_err(not_connected)??;
         let _ = self.sender.send(PnlPlantCommand::Close).await;
 
         Ok(r.remove(0))
     }
 
+    pub async fn select_account(&self, fcm_id: &str, ib_id: &str, account_id: &str) {
+        let command = PnlPlantCommand::SelectAccount {
+            fcm_id: fcm_id.to_string(),
+            ib_id: ib_id.to_string(),
+            account_id: account_id.to_string(),
+        };
+
+        let _ = self.sender.send(command).await;
+    }
+
     pub async fn subscribe_pnl_updates(&self) -> Result<RithmicResponse, String> {
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
@@ -336,9 +572,9 @@ impl RithmicPnlPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        Ok(rx.await.map_err(not_connected)??.remove(0))
     }
 
     pub async fn pnl_position_snapshots(&self) -> Result<RithmicResponse, String> {
@@ -348,8 +584,147 @@ impl RithmicPnlPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Account level pnl updates only, e.g. for an equity monitor. The protocol can't subscribe to
+    /// them without the instrument updates, these are dropped here. Call `subscribe_pnl_updates`
+    /// for the updates to flow. Stops when the receiver is dropped or the plant stops.
+    pub fn account_pnl_updates(&self) -> mpsc::Receiver<AccountPnLPositionUpdate> {
+        let mut updates = self.subscription_sender.subscribe();
+        let (tx, rx) = mpsc::channel(ACCOUNT_PNL_BUFFER_SIZE);
+
+        tokio::spawn(async move {
+            loop {
+                let update = tokio::select! {
+                    update = updates.recv() => update,
+                    _ = tx.closed() => break,
+                };
+
+                match update {
+                    Ok(response) => {
+                        if let RithmicMessage::AccountPnLPositionUpdate(account) = response.message
+                            && tx.send(account).await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        rx
+    }
+
+    /// Account and positions as replayed by the server in answer to pnl_position_snapshots
+    pub async fn position_snapshot(&self) -> Result<PositionSnapshot, String> {
+        let mut updates = self.subscription_sender.subscribe();
+
+        self.pnl_position_snapshots().await?;
+
+        let mut snapshot = PositionSnapshot::default();
 
-        Ok(rx.await.unwrap().unwrap().remove(0))
+        for update in drain_updates(&mut updates)? {
+            match update.message {
+                RithmicMessage::AccountPnLPositionUpdate(account) => {
+                    snapshot.account = Some(account);
+                }
+                RithmicMessage::InstrumentPnLPositionUpdate(position) => {
+                    match snapshot
+                        .positions
+                        .iter()
+                        .position(|p| p.symbol == position.symbol && p.exchange == position.exchange)
+                    {
+                        Some(i) => snapshot.positions[i] = position,
+                        None => snapshot.positions.push(position),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// Account pnl and open positions at one point in time
+#[derive(Clone, Debug, Default)]
+pub struct PositionSnapshot {
+    pub account: Option<AccountPnLPositionUpdate>,
+    /// Latest update of each instrument
+    pub positions: Vec<InstrumentPnLPositionUpdate>,
+}
+
+impl PositionSnapshot {
+    pub fn balance(&self) -> Option<f64> {
+        self.account
+            .as_ref()?
+            .account_balance
+            .as_deref()?
+            .parse()
+            .ok()
+    }
+
+    pub fn available_buying_power(&self) -> Option<f64> {
+        self.account
+            .as_ref()?
+            .available_buying_power
+            .as_deref()?
+            .parse()
+            .ok()
+    }
+
+    /// Whether the account has the buying power for a trade needing `margin_required`, see
+    /// `RithmicTickerPlantHandle::margin_required`. None without an account update.
+    pub fn can_afford(&self, margin_required: f64) -> Option<bool> {
+        Some(self.available_buying_power()? >= margin_required)
+    }
+}
+
+/// Open orders, positions and balance of the selected account. A section that failed holds its
+/// error, the others are still filled in.
+#[derive(Clone, Debug)]
+pub struct AccountSnapshot {
+    pub open_orders: Result<Vec<RithmicOrderNotification>, String>,
+    pub positions: Result<PositionSnapshot, String>,
+    /// When the requests were sent
+    pub taken_at: SystemTime,
+}
+
+/// Fetch the open orders and the pnl snapshot concurrently, for a consistent view of the account
+pub async fn account_snapshot(
+    orders: &RithmicOrderPlantHandle,
+    pnl: &RithmicPnlPlantHandle,
+) -> AccountSnapshot {
+    let taken_at = SystemTime::now();
+
+    let (open_orders, positions) = tokio::join!(orders.open_orders(), pnl.position_snapshot());
+
+    AccountSnapshot {
+        open_orders,
+        positions,
+        taken_at,
+    }
+}
+
+/// The plant's actor stopped, e.g. its connection was closed or failed
+fn not_connected<E>(_: E) -> String {
+    plant_not_connected("pnl")
+}
+
+impl Clone for RithmicPnlPlantHandle {
+    fn clone(&self) -> Self {
+        RithmicPnlPlantHandle {
+            equity_curve: self.equity_curve.clone(),
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
+            sender: self.sender.clone(),
+            subscription_sender: self.subscription_sender.clone(),
+            subscription_receiver: self.subscription_sender.subscribe(),
+        }
     }
 }
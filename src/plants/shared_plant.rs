@@ -7,7 +7,7 @@ use tracing::{event, Level};
 
 use crate::{
     api::{
-        RithmicConnectionInfo,
+        GatewayRegion, RithmicConnectionInfo,
         receiver_api::{RithmicReceiverApi, RithmicResponse},
         sender_api::RithmicSenderApi,
         DEFAULT_RTI_WS_URL,
@@ -33,7 +33,7 @@ use tokio_tungstenite::{
 use tokio::{
     net::TcpStream,
     sync::{broadcast::Sender, oneshot},
-    time::Interval,
+    time::{Duration, Interval},
 };
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tungstenite::client::IntoClientRequest;
@@ -54,7 +54,15 @@ pub enum SharedPlantCommand {
     },
 }
 
+/// Time to wait for the bootstrap endpoint to answer a discovery request
+pub const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of times a failed discovery request is re-sent
+pub const DEFAULT_DISCOVERY_RETRIES: usize = 2;
+
 pub struct RithmicSharedPlant {
+    discovery_retries: usize,
+    discovery_timeout: Duration,
     rithmic_sender_api: RithmicSenderApi,
     rithmic_receiver_api: RithmicReceiverApi,
 }
@@ -65,63 +73,148 @@ impl RithmicSharedPlant {
         let rithmic_sender_api = RithmicSenderApi::new(&config);
         let rithmic_receiver_api = RithmicReceiverApi {
             source: "shared_plant".to_string(),
+            frame_capture: None,
+            template_version: config.template_version.clone(),
         };
 
         RithmicSharedPlant {
+            discovery_retries: DEFAULT_DISCOVERY_RETRIES,
+            discovery_timeout: DEFAULT_DISCOVERY_TIMEOUT,
             rithmic_sender_api,
             rithmic_receiver_api
         }
     }
 
+    /// Time to wait for each discovery request, `DEFAULT_DISCOVERY_TIMEOUT` by default
+    pub fn set_discovery_timeout(&mut self, timeout: Duration) {
+        self.discovery_timeout = timeout;
+    }
+
+    /// Number of times a discovery request that failed or timed out is re-sent, with a
+    /// doubling backoff starting at 500ms. `DEFAULT_DISCOVERY_RETRIES` by default
+    pub fn set_discovery_retries(&mut self, retries: usize) {
+        self.discovery_retries = retries;
+    }
+
     pub async fn rithmic_system_info(&mut self) -> Result<ResponseRithmicSystemInfo, anyhow::Error> {
-        let ws_stream = connect(DEFAULT_RTI_WS_URL).await.unwrap();
-        let (rithmic_sender, mut rithmic_reader) = ws_stream.split();
+        match self.discover(|| SharedPlantCommand::RithmicSystemInfo {}).await? {
+            RithmicMessage::ResponseRithmicSystemInfo(msg) => Ok(msg),
+            _ => Err(anyhow!("message is not a rithmic system info")),
+        }
+    }
 
-        let command = SharedPlantCommand::RithmicSystemInfo {};
-        self.handle_command(rithmic_sender, command).await;
-        if let Some(message) = rithmic_reader.next().await {
-            if let Ok(Message::Binary(data)) = message {
-                if let RithmicMessage::ResponseRithmicSystemInfo(msg) = self.rithmic_receiver_api.buf_to_message(data).unwrap().message {
-                    Ok(msg)
-                } else {
-                    Err(anyhow!("message is not a rithmic system info"))
-                }
-            } else {
-                Err(anyhow!("rithmic message error"))
-            }
+    /// Check `system_name` is one of the systems listed by the server, typos otherwise only
+    /// show up as a failed login
+    pub async fn verify_system_name(&mut self, system_name: &str) -> Result<(), anyhow::Error> {
+        let systems = self.rithmic_system_info().await?.system_name;
+
+        if systems.iter().any(|s| s == system_name) {
+            Ok(())
         } else {
-            Err(anyhow!("rithmic message error"))
+            Err(anyhow!(
+                "unknown system name {:?}, available systems: {}",
+                system_name,
+                systems.join(", ")
+            ))
         }
     }
 
     pub async fn rithmic_system_gateway_info(&mut self, system_name: String
     ) -> Result<ResponseRithmicSystemGatewayInfo, anyhow::Error> {
-        let ws_stream = connect(DEFAULT_RTI_WS_URL).await.unwrap();
-        let (rithmic_sender, mut rithmic_reader) = ws_stream.split();
-
-        let command = SharedPlantCommand::RithmicSystemGatewayInfo {
-            system_name,
+        let command = || SharedPlantCommand::RithmicSystemGatewayInfo {
+            system_name: system_name.clone(),
         };
-        self.handle_command(rithmic_sender, command).await;
-        if let Some(message) = rithmic_reader.next().await {
-            if let Ok(Message::Binary(data)) = message {
-                if let RithmicMessage::ResponseRithmicSystemGatewayInfo(msg) = self.rithmic_receiver_api.buf_to_message(data).unwrap().message {
-                    Ok(msg)
-                } else {
-                    Err(anyhow!("message is not a rithmic system gateway info"))
+
+        match self.discover(command).await? {
+            RithmicMessage::ResponseRithmicSystemGatewayInfo(msg) => Ok(msg),
+            _ => Err(anyhow!("message is not a rithmic system gateway info")),
+        }
+    }
+
+    /// Send a request to the bootstrap endpoint, re-sent up to `discovery_retries` times when
+    /// it can't be reached or doesn't answer within `discovery_timeout`
+    async fn discover<F>(&mut self, command: F) -> Result<RithmicMessage, anyhow::Error>
+    where
+        F: Fn() -> SharedPlantCommand,
+    {
+        let mut backoff = Duration::from_millis(500);
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+
+            match self.discover_once(command()).await {
+                Ok(message) => return Ok(message),
+                Err(e) if attempt <= self.discovery_retries => {
+                    event!(
+                        Level::WARN,
+                        "shared_plant: discovery attempt {} failed, retrying in {:?}: {}",
+                        attempt,
+                        backoff,
+                        e
+                    );
+
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    return Err(anyhow!("discovery failed after {} attempts: {}", attempt, e));
                 }
-            } else {
-                Err(anyhow!("rithmic message error"))
             }
+        }
+    }
+
+    async fn discover_once(&mut self, command: SharedPlantCommand) -> Result<RithmicMessage, anyhow::Error> {
+        let ws_stream = connect(DEFAULT_RTI_WS_URL).await?;
+        let (rithmic_sender, mut rithmic_reader) = ws_stream.split();
+
+        self.handle_command(rithmic_sender, command).await?;
+
+        let message = tokio::time::timeout(self.discovery_timeout, rithmic_reader.next())
+            .await
+            .map_err(|_| anyhow!("no response after {:?}", self.discovery_timeout))?;
+
+        match message {
+            Some(Ok(Message::Binary(data))) => self
+                .rithmic_receiver_api
+                .buf_to_message(data)
+                .map(|response| response.message)
+                .map_err(|e| anyhow!(e)),
+            _ => Err(anyhow!("rithmic message error")),
+        }
+    }
+
+    /// All gateway uris of a system, in the order returned by the server. Pass the first as
+    /// `RithmicConnectionInfo::url` and the others as `fallback_urls`.
+    pub async fn gateway_uris(&mut self, system_name: String) -> Result<Vec<String>, anyhow::Error> {
+        let info = self.rithmic_system_gateway_info(system_name.clone()).await?;
+
+        if info.gateway_uri.is_empty() {
+            Err(anyhow!("no gateway uri returned for system {}", system_name))
         } else {
-            Err(anyhow!("rithmic message error"))
+            Ok(info.gateway_uri)
         }
     }
 
+    /// `gateway_uris` with the uris of `region` first, e.g. `RithmicCredentials::region`.
+    /// Falls back to the server order when no uri is in the region or `region` is `None`.
+    pub async fn gateway_uris_in_region(
+        &mut self,
+        system_name: String,
+        region: Option<GatewayRegion>,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let uris = self.gateway_uris(system_name).await?;
+
+        Ok(match region {
+            Some(region) => region.prefer(uris),
+            None => uris,
+        })
+    }
+
     async fn handle_command(
         &mut self,
         mut rithmic_sender: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message,>,
-        command: SharedPlantCommand) {
+        command: SharedPlantCommand) -> Result<(), Error> {
         match command {
             SharedPlantCommand::RithmicSystemInfo {} => {
                 let (request_buf, id) = self.rithmic_sender_api.request_rithmic_system_info();
@@ -131,7 +224,6 @@ impl RithmicSharedPlant {
                 rithmic_sender
                     .send(Message::Binary(request_buf))
                     .await
-                    .unwrap();
             }
             SharedPlantCommand::RithmicSystemGatewayInfo { system_name } => {
                 let (request_buf, id) = self.rithmic_sender_api.request_rithmic_system_gateway_info(
@@ -143,7 +235,6 @@ impl RithmicSharedPlant {
                 rithmic_sender
                     .send(Message::Binary(request_buf))
                     .await
-                    .unwrap();
             }
         }
     }
@@ -1,19 +1,31 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
 use async_trait::async_trait;
 use tracing::{event, Level};
 
 use crate::{
     api::{
-        RithmicConnectionInfo,
+        RithmicConnectionInfo, UnheardUpdates,
+        frame_capture::{CapturedFrame, FrameCapture},
         receiver_api::{RithmicReceiverApi, RithmicResponse},
+        rithmic_command_types::{
+            ExpirationDate, InstrumentType, RithmicBbo, SubscriptionStatus, TradeabilityReport,
+        },
         sender_api::RithmicSenderApi,
     },
+    plants::order_plant::{CONNECTION_LOST, RithmicOrderPlantHandle},
     request_handler::{RithmicRequest, RithmicRequestHandler},
     rti::{
+        messages::RithmicMessage,
         request_login::SysInfraType,
         request_market_data_update::{Request, UpdateBits},
-        request_search_symbols::InstrumentType,
+        BestBidOffer, LastTrade, MarketMode, ResponseReferenceData,
     },
-    ws::{get_heartbeat_interval, PlantActor, RithmicStream, connect},
+    ws::{ConnectionMonitor, ConnectionStats, IdleTimer, ServerClock, UnsubscribeResponses, await_unsubscribes, get_heartbeat_interval, plant_not_connected, wait_for_update, PlantActor, RithmicStream, connect_with_failover},
 };
 
 use futures_util::{
@@ -30,12 +42,37 @@ use tokio_tungstenite::{
 
 use tokio::{
     net::TcpStream,
-    sync::{broadcast::Sender, oneshot},
+    sync::{
+        broadcast::{self, error::RecvError, Sender},
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
     time::Interval,
 };
 
+/// Capacity of the channels of `RithmicTickerPlantHandle::trades`, `quotes` and
+/// `market_data_feed`
+const FEED_BUFFER_SIZE: usize = 1024;
+
 pub enum TickerPlantCommand {
+    AcquireMarketData {
+        symbol: String,
+        exchange: String,
+        fields: Vec<UpdateBits>,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
+    CachedFrontMonthContract {
+        symbol: String,
+        exchange: String,
+        response_sender: oneshot::Sender<Option<String>>,
+    },
     Close,
+    FrontMonthContract {
+        symbol: String,
+        exchange: String,
+        need_updates: bool,
+        response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
+    },
     GetInstrumentByUnderlying {
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
@@ -55,6 +92,10 @@ pub enum TickerPlantCommand {
         exact_search: Option<bool>,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    ReleaseMarketData {
+        symbol: String,
+        exchange: String,
+    },
     ReferenceData {
         symbol: Option<String>,
         exchange: Option<String>,
@@ -62,6 +103,10 @@ pub enum TickerPlantCommand {
     },
     SendHeartbeat {},
     SetLogin,
+    SetMarketDataPaused {
+        paused: bool,
+        response_sender: oneshot::Sender<Vec<(String, String, Vec<UpdateBits>)>>,
+    },
     Subscribe {
         symbol: String,
         exchange: String,
@@ -69,32 +114,94 @@ pub enum TickerPlantCommand {
         request_type: Request,
         response_sender: oneshot::Sender<Result<Vec<RithmicResponse>, String>>,
     },
+    SubscriptionStatus {
+        symbol: String,
+        exchange: String,
+        response_sender: oneshot::Sender<Option<SubscriptionStatus>>,
+    },
+    UnsubscribeAll {
+        response_sender: oneshot::Sender<UnsubscribeResponses>,
+    },
 }
 
+/// Logical subscribers of a symbol and the union of the fields they requested
+#[derive(Debug, Default)]
+struct MarketDataSubscription {
+    count: usize,
+    fields: Vec<UpdateBits>,
+}
+
+/// Reference data by (symbol, exchange), shared by the handles of a plant
+type ReferenceDataCache = Arc<Mutex<HashMap<(String, String), ResponseReferenceData>>>;
+
+/// Last session vwap by (symbol, exchange), from the `LastTrade` updates
+type VwapCache = Arc<Mutex<HashMap<(String, String), f64>>>;
+
+/// Best bid and offer by (symbol, exchange), from the `BestBidOffer` updates
+type BboCache = Arc<Mutex<HashMap<(String, String), RithmicBbo>>>;
+
+/// Margin rate by (symbol, exchange), from the `SymbolMarginRate` updates
+type MarginRateCache = Arc<Mutex<HashMap<(String, String), f64>>>;
+
+/// Last market mode by (symbol, exchange), from the `MarketMode` updates
+type MarketModeCache = Arc<Mutex<HashMap<(String, String), MarketMode>>>;
+
 pub struct RithmicTickerPlant {
+    bbos: BboCache,
     pub connection_handle: tokio::task::JoinHandle<()>,
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
+    margin_rates: MarginRateCache,
+    market_modes: MarketModeCache,
+    reference_data_cache: ReferenceDataCache,
     sender: tokio::sync::mpsc::Sender<TickerPlantCommand>,
+    server_clock: ServerClock,
     subscription_sender: Sender<RithmicResponse>,
+    vwaps: VwapCache,
 }
 
 impl RithmicTickerPlant {
+    /// Panics when the gateway can't be reached, see `try_new`
     pub async fn new(conn_info: &RithmicConnectionInfo) -> RithmicTickerPlant {
+        Self::try_new(conn_info).await.unwrap()
+    }
+
+    /// Connect to the gateway, failing instead of panicking so an application can carry on
+    /// without this plant
+    pub async fn try_new(conn_info: &RithmicConnectionInfo) -> Result<RithmicTickerPlant, String> {
         let (req_tx, req_rx) = tokio::sync::mpsc::channel::<TickerPlantCommand>(32);
-        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(1024);
+        let (sub_tx, _sub_rx) = tokio::sync::broadcast::channel(conn_info.update_buffer_size.max(1));
 
-        let mut ticker_plant = TickerPlant::new(req_rx, sub_tx.clone(), conn_info)
-            .await
-            .unwrap();
+        let frame_capture = FrameCapture::from_capacity("ticker_plant", conn_info.capture_raw_frames);
+
+        let mut ticker_plant = TickerPlant::new(req_rx, sub_tx.clone(), conn_info, frame_capture.clone())
+            .await?;
+
+        let server_clock = ticker_plant.server_clock.clone();
+        let vwaps = ticker_plant.vwaps.clone();
+        let bbos = ticker_plant.bbos.clone();
+        let margin_rates = ticker_plant.margin_rates.clone();
+        let market_modes = ticker_plant.market_modes.clone();
+
+        let connection_monitor = ticker_plant.connection_monitor.clone();
 
         let connection_handle = tokio::spawn(async move {
             ticker_plant.run().await;
         });
 
-        RithmicTickerPlant {
+        Ok(RithmicTickerPlant {
+            bbos,
             connection_handle,
+            connection_monitor,
+            frame_capture,
+            margin_rates,
+            market_modes,
+            reference_data_cache: ReferenceDataCache::default(),
             sender: req_tx,
+            server_clock,
             subscription_sender: sub_tx,
-        }
+            vwaps,
+        })
     }
 }
 
@@ -103,18 +210,37 @@ impl RithmicStream for RithmicTickerPlant {
 
     fn get_handle(&self) -> RithmicTickerPlantHandle {
         RithmicTickerPlantHandle {
+            bbos: self.bbos.clone(),
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
+            margin_rates: self.margin_rates.clone(),
+            market_modes: self.market_modes.clone(),
+            reference_data_cache: self.reference_data_cache.clone(),
             sender: self.sender.clone(),
+            server_clock: self.server_clock.clone(),
             subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
+            vwaps: self.vwaps.clone(),
         }
     }
 }
 
 #[derive(Debug)]
 pub struct TickerPlant {
+    bbos: BboCache,
     config: RithmicConnectionInfo,
+    connection_monitor: ConnectionMonitor,
+    /// Current contract by (root symbol, exchange), kept up to date by the roll updates
+    front_month_contracts: HashMap<(String, String), String>,
+    /// Root symbol and exchange of the pending front month requests by request id
+    front_month_requests: HashMap<String, (String, String)>,
+    idle_timer: IdleTimer,
     interval: Interval,
     logged_in: bool,
+    margin_rates: MarginRateCache,
+    market_data_paused: bool,
+    market_data_subscriptions: HashMap<(String, String), MarketDataSubscription>,
+    market_modes: MarketModeCache,
     request_handler: RithmicRequestHandler,
     request_receiver: tokio::sync::mpsc::Receiver<TickerPlantCommand>,
     rithmic_reader: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
@@ -125,7 +251,13 @@ pub struct TickerPlant {
     >,
 
     rithmic_sender_api: RithmicSenderApi,
+    server_clock: ServerClock,
+    /// Symbol and exchange of the pending market data subscribe requests by request id
+    subscription_requests: HashMap<String, (String, String)>,
     subscription_sender: Sender<RithmicResponse>,
+    /// Outcome of the last subscribe request by (symbol, exchange), until unsubscribed
+    subscription_statuses: HashMap<(String, String), SubscriptionStatus>,
+    vwaps: VwapCache,
 }
 
 impl TickerPlant {
@@ -133,31 +265,145 @@ impl TickerPlant {
         request_receiver: tokio::sync::mpsc::Receiver<TickerPlantCommand>,
         subscription_sender: Sender<RithmicResponse>,
         conn_info: &RithmicConnectionInfo,
-    ) -> Result<TickerPlant, ()> {
-        let config = conn_info.clone();
+        frame_capture: Option<FrameCapture>,
+    ) -> Result<TickerPlant, String> {
+        let mut config = conn_info.clone();
 
-        let ws_stream = connect(&config.url).await.unwrap();
+        let (ws_stream, url) = connect_with_failover(&config.gateway_urls(), &config.ws_headers)
+            .await
+            .map_err(|e| format!("ticker_plant: {}", e))?;
+        // Reconnect to the gateway that worked first
+        config.url = url;
         let (rithmic_sender, rithmic_reader) = ws_stream.split();
-        let rithmic_sender_api = RithmicSenderApi::new(&config);
+        let mut rithmic_sender_api = RithmicSenderApi::new(&config);
+        rithmic_sender_api.set_frame_capture(frame_capture.clone());
+
         let rithmic_receiver_api = RithmicReceiverApi {
             source: "ticker_plant".to_string(),
+            frame_capture,
+            template_version: config.template_version.clone(),
         };
 
+        let idle_timer = IdleTimer::new(config.auto_logout_after_secs);
         let interval = get_heartbeat_interval();
 
         Ok(TickerPlant {
+            bbos: BboCache::default(),
             config,
+            connection_monitor: ConnectionMonitor::default(),
+            front_month_contracts: HashMap::new(),
+            front_month_requests: HashMap::new(),
+            idle_timer,
             interval,
             logged_in: false,
+            margin_rates: MarginRateCache::default(),
+            market_data_paused: false,
+            market_data_subscriptions: HashMap::new(),
+            market_modes: MarketModeCache::default(),
             request_handler: RithmicRequestHandler::new(),
             request_receiver,
             rithmic_reader,
             rithmic_receiver_api,
             rithmic_sender_api,
             rithmic_sender,
+            server_clock: ServerClock::default(),
+            subscription_requests: HashMap::new(),
+            subscription_statuses: HashMap::new(),
             subscription_sender,
+            vwaps: VwapCache::default(),
         })
     }
+
+    /// Cache the contract returned for a front month request, and follow the rolls reported by
+    /// the updates of the requests sent with `need_updates`
+    fn track_front_month_contract(&mut self, response: &RithmicResponse) {
+        match &response.message {
+            RithmicMessage::ResponseFrontMonthContract(resp) => {
+                let Some(key) = self.front_month_requests.remove(&response.request_id) else {
+                    return;
+                };
+
+                if let (None, Some(contract)) = (&response.error, &resp.trading_symbol) {
+                    self.front_month_contracts.insert(key, contract.clone());
+                }
+            }
+            RithmicMessage::FrontMonthContractUpdate(update) => {
+                if update.is_front_month_symbol != Some(true) {
+                    return;
+                }
+
+                if let (Some(symbol), Some(exchange), Some(contract)) =
+                    (&update.symbol, &update.exchange, &update.trading_symbol)
+                {
+                    event!(
+                        Level::INFO,
+                        "ticker_plant: front month of {} on {} is now {}",
+                        symbol,
+                        exchange,
+                        contract
+                    );
+
+                    self.front_month_contracts
+                        .insert((symbol.clone(), exchange.clone()), contract.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a frame to the gateway. A failed send is logged and fails the pending requests,
+    /// the actor keeps running so every handle gets an error rather than a stopped plant
+    async fn send_frame(&mut self, message: Message) {
+        if let Err(e) = self.rithmic_sender.send(message).await {
+            event!(Level::ERROR, "ticker_plant: send failed {}", e);
+
+            self.connection_monitor.error(&e);
+            self.request_handler.fail_pending(CONNECTION_LOST);
+        }
+    }
+
+    /// Record whether the gateway accepted a market data subscribe request
+    fn track_subscription_status(&mut self, response: &RithmicResponse) {
+        if let RithmicMessage::ResponseMarketDataUpdate(_) = &response.message
+            && let Some(key) = self.subscription_requests.remove(&response.request_id)
+        {
+            let status = SubscriptionStatus::from_response(response);
+
+            if let SubscriptionStatus::Rejected { reason } = &status {
+                event!(
+                    Level::WARN,
+                    "ticker_plant: market data subscription of {} on {} rejected: {}",
+                    key.0,
+                    key.1,
+                    reason
+                );
+            }
+
+            self.subscription_statuses.insert(key, status);
+        }
+    }
+
+    /// Log out and close the connection once no request has been sent for `auto_logout_after_secs`
+    async fn auto_logout(&mut self) {
+        event!(
+            Level::WARN,
+            "ticker_plant: no request for {}s, logging out",
+            self.config.auto_logout_after_secs
+        );
+
+        self.logged_in = false;
+
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        self.handle_command(TickerPlantCommand::Logout { response_sender: tx }).await;
+        self.handle_command(TickerPlantCommand::Close).await;
+
+        tokio::spawn(async move {
+            if let Ok(Err(e)) = rx.await {
+                event!(Level::ERROR, "ticker_plant: auto logout failed: {}", e);
+            }
+        });
+    }
 }
 
 #[async_trait]
@@ -172,11 +418,15 @@ impl PlantActor for TickerPlant {
         loop {
             tokio::select! {
                 _ = self.interval.tick() => {
-                    if self.logged_in {
+                    if self.logged_in && self.idle_timer.expired() {
+                        self.auto_logout().await;
+                    } else if self.logged_in {
                         self.handle_command(TickerPlantCommand::SendHeartbeat {}).await;
                     }
                 }
                 Some(message) = self.request_receiver.recv() => {
+                    self.idle_timer.touch();
+
                     self.handle_command(message).await;
                 }
                 Some(message) = self.rithmic_reader.next() => {
@@ -207,15 +457,69 @@ impl PlantActor for TickerPlant {
 
                 stop = true;
             }
-            Ok(Message::Binary(data)) => {
-                let response = self.rithmic_receiver_api.buf_to_message(data).unwrap();
+            Ok(Message::Binary(data)) => match self.rithmic_receiver_api.buf_to_message(data) {
+                Ok(response) => {
+                    if let RithmicMessage::ResponseHeartbeat(heartbeat) = &response.message {
+                        self.server_clock.heartbeat_received(heartbeat.ssboe, heartbeat.usecs);
+                    }
 
-                if response.is_update {
-                    self.subscription_sender.send(response).unwrap();
-                } else {
-                    self.request_handler.handle_response(response);
+                    self.track_front_month_contract(&response);
+                    self.track_subscription_status(&response);
+
+                    if let RithmicMessage::LastTrade(trade) = &response.message
+                        && let (Some(symbol), Some(exchange), Some(vwap)) =
+                            (&trade.symbol, &trade.exchange, trade.vwap)
+                    {
+                        self.vwaps
+                            .lock()
+                            .unwrap()
+                            .insert((symbol.clone(), exchange.clone()), vwap);
+                    }
+
+                    if let RithmicMessage::BestBidOffer(update) = &response.message
+                        && let (Some(symbol), Some(exchange)) = (&update.symbol, &update.exchange)
+                    {
+                        self.bbos
+                            .lock()
+                            .unwrap()
+                            .entry((symbol.clone(), exchange.clone()))
+                            .or_default()
+                            .apply(update);
+                    }
+
+                    if let RithmicMessage::MarketMode(mode) = &response.message
+                        && let (Some(symbol), Some(exchange)) = (&mode.symbol, &mode.exchange)
+                    {
+                        self.market_modes
+                            .lock()
+                            .unwrap()
+                            .insert((symbol.clone(), exchange.clone()), mode.clone());
+                    }
+
+                    if let RithmicMessage::SymbolMarginRate(rate) = &response.message
+                        && let (Some(symbol), Some(exchange), Some(margin_rate)) =
+                            (&rate.symbol, &rate.exchange, rate.margin_rate)
+                    {
+                        self.margin_rates
+                            .lock()
+                            .unwrap()
+                            .insert((symbol.clone(), exchange.clone()), margin_rate);
+                    }
+
+                    if self.request_handler.dispatch(response, &self.subscription_sender)
+                        && self.config.unheard_updates == UnheardUpdates::Close
+                    {
+                        event!(Level::WARN, "ticker_plant: no update receiver left, closing");
+
+                        self.handle_command(TickerPlantCommand::Close).await;
+                    }
                 }
-            }
+                Err(e) => {
+                    event!(Level::ERROR, "ticker_plant: response from server: {:?}", e);
+
+                    self.connection_monitor.error(&e);
+                }
+            },
             Err(Error::ConnectionClosed) => {
                 event!(Level::INFO, "ticker_plant connection closed");
 
@@ -227,6 +531,10 @@ impl PlantActor for TickerPlant {
                     "ticker_plant received unknown message {:?}",
                     message
                 );
+
+                if let Err(e) = &message {
+                    self.connection_monitor.error(e);
+                }
             }
         }
 
@@ -235,6 +543,59 @@ impl PlantActor for TickerPlant {
 
     async fn handle_command(&mut self, command: TickerPlantCommand) {
         match command {
+            TickerPlantCommand::AcquireMarketData {
+                symbol,
+                exchange,
+                fields,
+                response_sender,
+            } => {
+                if !self.config.allows_subscription(&symbol, &exchange) {
+                    let _ = response_sender.send(Err(subscription_denied(&symbol, &exchange)));
+                    return;
+                }
+
+                let subscription = self
+                    .market_data_subscriptions
+                    .entry((symbol.clone(), exchange.clone()))
+                    .or_default();
+
+                subscription.count += 1;
+
+                let new_fields: Vec<UpdateBits> = fields
+                    .into_iter()
+                    .filter(|field| !subscription.fields.contains(field))
+                    .collect();
+
+                // Already subscribed with every requested field, nothing to send
+                if subscription.count > 1 && new_fields.is_empty() {
+                    let _ = response_sender.send(Ok(vec![]));
+                    return;
+                }
+
+                subscription.fields.extend(new_fields);
+
+                // Sent to the gateway on resume
+                if self.market_data_paused {
+                    let _ = response_sender.send(Ok(vec![]));
+                    return;
+                }
+
+                let (sub_buf, id) = self.rithmic_sender_api.request_market_data_update(
+                    &symbol,
+                    &exchange,
+                    subscription.fields.clone(),
+                    Request::Subscribe,
+                );
+
+                self.subscription_requests.insert(id.clone(), (symbol, exchange));
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                self.send_frame(Message::Binary(sub_buf)).await;
+            }
             TickerPlantCommand::Close => {
                 self.rithmic_sender
                     .send(Message::Close(None))
@@ -288,6 +649,36 @@ impl PlantActor for TickerPlant {
                     .await
                     .unwrap();
             }
+            TickerPlantCommand::CachedFrontMonthContract {
+                symbol,
+                exchange,
+                response_sender,
+            } => {
+                let contract = self.front_month_contracts.get(&(symbol, exchange)).cloned();
+
+                let _ = response_sender.send(contract);
+            }
+            TickerPlantCommand::FrontMonthContract {
+                symbol,
+                exchange,
+                need_updates,
+                response_sender,
+            } => {
+                let (request_buf, id) = self
+                    .rithmic_sender_api
+                    .request_front_month_contract(&symbol, &exchange, need_updates);
+
+                self.front_month_requests.insert(id.clone(), (symbol, exchange));
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: response_sender,
+                });
+
+                let _ = self.rithmic_sender
+                    .send(Message::Binary(request_buf))
+                    .await;
+            }
             TickerPlantCommand::ProductCodes { exchange , response_sender} => {
                 let (request_buf, id) = self.rithmic_sender_api.request_product_codes(exchange);
 
@@ -301,6 +692,50 @@ impl PlantActor for TickerPlant {
                     .await
                     .unwrap();
             }
+            TickerPlantCommand::ReleaseMarketData { symbol, exchange } => {
+                let key = (symbol, exchange);
+
+                let Some(subscription) = self.market_data_subscriptions.get_mut(&key) else {
+                    return;
+                };
+
+                subscription.count -= 1;
+
+                if subscription.count > 0 {
+                    return;
+                }
+
+                let subscription = self.market_data_subscriptions.remove(&key).unwrap();
+                self.subscription_statuses.remove(&key);
+                let (symbol, exchange) = key;
+
+                // Already unsubscribed on pause
+                if self.market_data_paused {
+                    return;
+                }
+
+                let (unsub_buf, id) = self.rithmic_sender_api.request_market_data_update(
+                    &symbol,
+                    &exchange,
+                    subscription.fields,
+                    Request::Unsubscribe,
+                );
+
+                let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                self.request_handler.register_request(RithmicRequest {
+                    request_id: id,
+                    responder: tx,
+                });
+
+                self.send_frame(Message::Binary(unsub_buf)).await;
+
+                tokio::spawn(async move {
+                    if let Ok(Err(e)) = rx.await {
+                        event!(Level::ERROR, "ticker_plant: unsubscribe {} failed: {}", symbol, e);
+                    }
+                });
+            }
             TickerPlantCommand::ReferenceData { symbol, exchange , response_sender} => {
                 let (request_buf, id) = self.rithmic_sender_api.request_reference_data(
                     symbol, exchange
@@ -319,7 +754,7 @@ impl PlantActor for TickerPlant {
             TickerPlantCommand::SearchSymbols { search_text , instrument_type, exact_search, response_sender} => {
                 let (request_buf, id) = self.rithmic_sender_api.request_search_symbols(
                     search_text,
-                    instrument_type,
+                    instrument_type.map(Into::into),
                     exact_search
                 );
 
@@ -336,6 +771,8 @@ impl PlantActor for TickerPlant {
             TickerPlantCommand::SendHeartbeat {} => {
                 let (heartbeat_buf, _id) = self.rithmic_sender_api.request_heartbeat();
 
+                self.server_clock.heartbeat_sent();
+
                 let _ = self
                     .rithmic_sender
                     .send(Message::Binary(heartbeat_buf))
@@ -344,6 +781,22 @@ impl PlantActor for TickerPlant {
             TickerPlantCommand::SetLogin => {
                 self.logged_in = true;
             }
+            TickerPlantCommand::SetMarketDataPaused {
+                paused,
+                response_sender,
+            } => {
+                self.market_data_paused = paused;
+
+                let subscriptions = self
+                    .market_data_subscriptions
+                    .iter()
+                    .map(|((symbol, exchange), subscription)| {
+                        (symbol.clone(), exchange.clone(), subscription.fields.clone())
+                    })
+                    .collect();
+
+                let _ = response_sender.send(subscriptions);
+            }
             TickerPlantCommand::Subscribe {
                 symbol,
                 exchange,
@@ -351,6 +804,13 @@ impl PlantActor for TickerPlant {
                 request_type,
                 response_sender,
             } => {
+                if request_type == Request::Subscribe
+                    && !self.config.allows_subscription(&symbol, &exchange)
+                {
+                    let _ = response_sender.send(Err(subscription_denied(&symbol, &exchange)));
+                    return;
+                }
+
                 let (sub_buf, id) = self.rithmic_sender_api.request_market_data_update(
                     &symbol,
                     &exchange,
@@ -358,6 +818,12 @@ impl PlantActor for TickerPlant {
                     request_type,
                 );
 
+                if request_type == Request::Subscribe {
+                    self.subscription_requests.insert(id.clone(), (symbol, exchange));
+                } else {
+                    self.subscription_statuses.remove(&(symbol, exchange));
+                }
+
                 self.request_handler.register_request(RithmicRequest {
                     request_id: id,
                     responder: response_sender,
@@ -368,18 +834,176 @@ impl PlantActor for TickerPlant {
                     .await
                     .unwrap();
             }
+            TickerPlantCommand::SubscriptionStatus {
+                symbol,
+                exchange,
+                response_sender,
+            } => {
+                let status = self.subscription_statuses.get(&(symbol, exchange)).cloned();
+
+                let _ = response_sender.send(status);
+            }
+            TickerPlantCommand::UnsubscribeAll { response_sender } => {
+                let mut responses = Vec::new();
+
+                // Already unsubscribed on pause
+                if !self.market_data_paused {
+                    for ((symbol, exchange), subscription) in &self.market_data_subscriptions {
+                        let (unsub_buf, id) = self.rithmic_sender_api.request_market_data_update(
+                            symbol,
+                            exchange,
+                            subscription.fields.clone(),
+                            Request::Unsubscribe,
+                        );
+
+                        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+                        self.request_handler.register_request(RithmicRequest {
+                            request_id: id,
+                            responder: tx,
+                        });
+
+                        let _ = self.rithmic_sender.send(Message::Binary(unsub_buf)).await;
+
+                        responses.push(rx);
+                    }
+                }
+
+                // Guards dropped after the disconnect must not unsubscribe again
+                self.market_data_paused = true;
+
+                let _ = response_sender.send(responses);
+            }
         }
     }
 }
 
+/// Symbol and exchange of a market data update
+fn update_instrument(message: &RithmicMessage) -> Option<(&Option<String>, &Option<String>)> {
+    match message {
+        RithmicMessage::BestBidOffer(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::DepthByOrder(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::EndOfDayPrices(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::LastTrade(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::MarketMode(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::OpenInterest(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::OrderBook(m) => Some((&m.symbol, &m.exchange)),
+        RithmicMessage::SymbolMarginRate(m) => Some((&m.symbol, &m.exchange)),
+        _ => None,
+    }
+}
+
+/// Whether an update is about the (symbol, exchange) of `key`
+fn is_instrument(symbol: &Option<String>, exchange: &Option<String>, key: &(String, String)) -> bool {
+    symbol.as_ref() == Some(&key.0) && exchange.as_ref() == Some(&key.1)
+}
+
+fn subscription_denied(symbol: &str, exchange: &str) -> String {
+    format!(
+        "subscription denied: {} on {} is not in the subscription allowlist",
+        symbol, exchange
+    )
+}
+
+/// Cloning a handle is cheap: clones share the plant's command channel and each gets its own
+/// update receiver. Handles are `Send + Sync`, clone one into each task issuing requests.
 pub struct RithmicTickerPlantHandle {
+    bbos: BboCache,
+    connection_monitor: ConnectionMonitor,
+    frame_capture: Option<FrameCapture>,
+    margin_rates: MarginRateCache,
+    market_modes: MarketModeCache,
+    reference_data_cache: ReferenceDataCache,
     sender: tokio::sync::mpsc::Sender<TickerPlantCommand>,
+    server_clock: ServerClock,
     // Used for cloning
     subscription_sender: tokio::sync::broadcast::Sender<RithmicResponse>,
     pub subscription_receiver: tokio::sync::broadcast::Receiver<RithmicResponse>,
+    vwaps: VwapCache,
 }
 
 impl RithmicTickerPlantHandle {
+    /// Last raw frames sent and received by the plant, empty unless
+    /// `RithmicConnectionInfo::capture_raw_frames` is set
+    pub fn recent_frames(&self) -> Vec<CapturedFrame> {
+        self.frame_capture
+            .as_ref()
+            .map(|frame_capture| frame_capture.recent_frames())
+            .unwrap_or_default()
+    }
+
+    /// Since when the plant has been connected, how many times it reconnected and its last
+    /// connection or server error
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_monitor.stats()
+    }
+
+    /// Estimated gateway time minus local time in microseconds, positive when the gateway clock
+    /// is ahead. Updated on every heartbeat response, None until the first one is received.
+    pub fn server_time_offset(&self) -> Option<i64> {
+        self.server_clock.offset_usecs()
+    }
+
+    /// Session vwap of a subscribed instrument, the `vwap` field of its last `LastTrade` update.
+    /// `TradeStatistics` only carries the session open, high and low.
+    pub fn vwap(&self, symbol: &str, exchange: &str) -> Option<f64> {
+        self.vwaps
+            .lock()
+            .unwrap()
+            .get(&(symbol.to_string(), exchange.to_string()))
+            .copied()
+    }
+
+    /// Best bid and offer of a subscribed instrument with their sizes and order counts, merged
+    /// from its `BestBidOffer` updates. Subscribe with `UpdateBits::Bbo`.
+    pub fn bbo(&self, symbol: &str, exchange: &str) -> Option<RithmicBbo> {
+        self.bbos
+            .lock()
+            .unwrap()
+            .get(&(symbol.to_string(), exchange.to_string()))
+            .cloned()
+    }
+
+    /// Last margin rate of a subscribed instrument, the margin of one contract. Subscribe with
+    /// `UpdateBits::MarginRate`.
+    pub fn margin_rate(&self, symbol: &str, exchange: &str) -> Option<f64> {
+        self.margin_rates
+            .lock()
+            .unwrap()
+            .get(&(symbol.to_string(), exchange.to_string()))
+            .copied()
+    }
+
+    /// Margin needed to hold `qty` contracts, from the last margin rate. Compare it with
+    /// `PositionSnapshot::available_buying_power` before placing an order.
+    pub fn margin_required(&self, symbol: &str, exchange: &str, qty: i32) -> Option<f64> {
+        Some(self.margin_rate(symbol, exchange)? * f64::from(qty.abs()))
+    }
+
+    /// Last market mode of a subscribed instrument, e.g. open, closed or halted with its
+    /// `halt_reason`. Subscribe with `UpdateBits::MarketMode`. The protocol has no session
+    /// hours, this is the only way to know whether the market is open.
+    pub fn market_mode(&self, symbol: &str, exchange: &str) -> Option<MarketMode> {
+        self.market_modes
+            .lock()
+            .unwrap()
+            .get(&(symbol.to_string(), exchange.to_string()))
+            .cloned()
+    }
+
+    /// Wait for the first update on this handle's receiver matching `predicate`, e.g. the fill
+    /// of an order just placed. Updates received before the match are consumed.
+    pub async fn wait_for<F>(
+        &mut self,
+        predicate: F,
+        timeout: std::time::Duration,
+    ) -> Result<RithmicResponse, String>
+    where
+        F: Fn(&RithmicResponse) -> bool,
+    {
+        wait_for_update(&mut self.subscription_receiver, predicate, timeout).await
+    }
+
     pub async fn login(&self) -> Result<RithmicResponse, String> {
         event!(Level::INFO, "ticker_plant: logging in");
 
@@ -389,35 +1013,45 @@ impl RithmicTickerPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let response = rx.await.unwrap().unwrap().remove(0);
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        if response.error.is_none() {
-            let _ = self.sender.send(TickerPlantCommand::SetLogin).await;
+        match rx.await.map_err(not_connected)? {
+            Ok(mut responses) => {
+                let _ = self.sender.send(TickerPlantCommand::SetLogin).await;
 
-            event!(Level::INFO, "ticker_plant: logged in");
+                event!(Level::INFO, "ticker_plant: logged in");
 
-            Ok(response)
-        } else {
-            event!(
-                Level::ERROR,
-                "ticker_plant: login failed {:?}",
-                response.error
-            );
+                Ok(responses.remove(0))
+            }
+            Err(e) => {
+                event!(Level::ERROR, "ticker_plant: login failed {}", e);
 
-            Err(response.error.unwrap())
+                Err(e)
+            }
         }
     }
 
+    /// Unsubscribe the feeds of `subscribe_market_data`, then log out and close the connection
     pub async fn disconnect(&self) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel();
+
+        let _ = self
+            .sender
+            .send(TickerPlantCommand::UnsubscribeAll { response_sender: tx })
+            .await;
+
+        if let Ok(responses) = rx.await {
+            await_unsubscribes("ticker_plant", responses).await;
+        }
+
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = TickerPlantCommand::Logout {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
-        let mut r = rx.await.unwrap().unwrap();
+        self.sender.send(command).await.map_err(not_connected)?;
+        let mut r = rx.await.map_err(not_connected)??;
         let _ = self.sender.send(TickerPlantCommand::Close).await;
         let response = r.remove(0);
 
@@ -433,9 +1067,56 @@ impl RithmicTickerPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        rx.await.map_err(not_connected)?
+    }
+
+    pub async fn front_month_contract(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        need_updates: bool,
+    ) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = TickerPlantCommand::FrontMonthContract {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            need_updates,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Current contract of a root symbol, e.g. "ESZ5" for "ES". Resolved once, then served from
+    /// a cache the plant updates when the server reports a roll to the next contract.
+    pub async fn current_contract(&self, root: &str, exchange: &str) -> Result<String, String> {
+        let (tx, rx) = oneshot::channel::<Option<String>>();
+
+        let command = TickerPlantCommand::CachedFrontMonthContract {
+            symbol: root.to_string(),
+            exchange: exchange.to_string(),
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        if let Some(contract) = rx.await.map_err(not_connected)? {
+            return Ok(contract);
+        }
+
+        let response = self.front_month_contract(root, exchange, true).await?;
 
-        Ok(rx.await.unwrap()?)
+        match response.message {
+            RithmicMessage::ResponseFrontMonthContract(resp) => resp
+                .trading_symbol
+                .ok_or_else(|| format!("no front month contract for {} on {}", root, exchange)),
+            _ => Err("unexpected response to front month contract request".to_string()),
+        }
     }
 
     pub async fn product_codes(&self,
@@ -448,11 +1129,12 @@ impl RithmicTickerPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?)
+        rx.await.map_err(not_connected)?
     }
 
+    /// Successful responses are cached, see `cached_reference_data`
     pub async fn reference_data(&self,
                                 symbol: Option<String>,
                                 exchange: Option<String>
@@ -460,14 +1142,82 @@ impl RithmicTickerPlantHandle {
         let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
 
         let command = TickerPlantCommand::ReferenceData {
-            symbol,
-            exchange,
+            symbol: symbol.clone(),
+            exchange: exchange.clone(),
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        let response = rx.await.map_err(not_connected)??.remove(0);
+
+        if let RithmicMessage::ResponseReferenceData(resp) = &response.message {
+            let symbol = symbol.or_else(|| resp.symbol.clone()).unwrap_or_default();
+            let exchange = exchange.or_else(|| resp.exchange.clone()).unwrap_or_default();
+
+            self.reference_data_cache
+                .lock()
+                .unwrap()
+                .insert((symbol, exchange), resp.clone());
+        }
+
+        Ok(response)
+    }
+
+    /// Fetch and cache the reference data of `symbols` concurrently. A failed symbol doesn't
+    /// abort the others, the failures are returned with their symbol.
+    pub async fn preload_reference_data(
+        &self,
+        exchange: &str,
+        symbols: &[&str],
+    ) -> Result<(), Vec<(String, String)>> {
+        let requests = symbols.iter().map(|symbol| async move {
+            let result = self
+                .reference_data(Some(symbol.to_string()), Some(exchange.to_string()))
+                .await
+                .map(|_| ());
+
+            (symbol.to_string(), result)
+        });
+
+        let failures: Vec<(String, String)> = futures_util::future::join_all(requests)
+            .await
+            .into_iter()
+            .filter_map(|(symbol, result)| result.err().map(|e| (symbol, e)))
+            .collect();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Reference data cached by a previous `reference_data` or `preload_reference_data` call
+    pub fn cached_reference_data(&self, symbol: &str, exchange: &str) -> Option<ResponseReferenceData> {
+        self.reference_data_cache
+            .lock()
+            .unwrap()
+            .get(&(symbol.to_string(), exchange.to_string()))
+            .cloned()
+    }
+
+    /// Minimum price increment of a cached instrument
+    pub fn tick_size(&self, symbol: &str, exchange: &str) -> Option<f64> {
+        self.cached_reference_data(symbol, exchange)
+            .and_then(|reference_data| reference_data.min_qprice_change)
+    }
+
+    /// Expiration date of a cached contract, None for an instrument that doesn't expire
+    pub fn expiration(&self, symbol: &str, exchange: &str) -> Option<ExpirationDate> {
+        self.cached_reference_data(symbol, exchange)
+            .and_then(|reference_data| ExpirationDate::from_reference_data(&reference_data))
+    }
 
-        Ok(rx.await.unwrap()?.remove(0))
+    /// Days left until a cached contract expires, from today's UTC date. 0 on the expiration
+    /// day, negative once expired.
+    pub fn days_to_expiry(&self, symbol: &str, exchange: &str) -> Option<i64> {
+        Some(self.expiration(symbol, exchange)?.days_from(SystemTime::now()))
     }
 
     pub async fn search_symbols(&self,
@@ -484,9 +1234,9 @@ impl RithmicTickerPlantHandle {
             response_sender: tx,
         };
 
-        let _ = self.sender.send(command).await;
+        self.sender.send(command).await.map_err(not_connected)?;
 
-        Ok(rx.await.unwrap()?)
+        rx.await.map_err(not_connected)?
     }
 
     pub async fn subscribe(
@@ -505,18 +1255,391 @@ impl RithmicTickerPlantHandle {
             response_sender: tx,
         };
 
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+
+    /// Whether the gateway accepted the last subscribe request of a symbol, e.g. rejected for
+    /// lack of entitlement. None when the symbol isn't subscribed or the response is pending.
+    pub async fn subscription_status(
+        &self,
+        symbol: &str,
+        exchange: &str,
+    ) -> Option<SubscriptionStatus> {
+        let (tx, rx) = oneshot::channel::<Option<SubscriptionStatus>>();
+
+        let command = TickerPlantCommand::SubscriptionStatus {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            response_sender: tx,
+        };
+
         let _ = self.sender.send(command).await;
 
-        Ok(rx.await.unwrap()?.remove(0))
+        rx.await.ok()?
+    }
+
+    /// Subscribe to last trade, BBO and settlement prices.
+    ///
+    /// Rithmic has no separate snapshot request: on subscribe the gateway first sends the current
+    /// value of each requested field with `is_snapshot` set, then streams updates. Requesting these
+    /// fields together means the first events received carry the current market state.
+    pub async fn subscribe_market_data_with_snapshot(
+        &self,
+        symbol: &str,
+        exchange: &str,
+    ) -> Result<RithmicResponse, String> {
+        self.subscribe(
+            symbol,
+            exchange,
+            vec![UpdateBits::LastTrade, UpdateBits::Bbo, UpdateBits::Settlement],
+        )
+        .await
+    }
+
+    /// Reference counted subscription: the symbol stays subscribed on the gateway until the
+    /// guards of every subscriber are dropped, so one part of an application unsubscribing
+    /// doesn't stop the feed for another. Fields requested by later subscribers are added to
+    /// the gateway subscription.
+    pub async fn subscribe_market_data(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        fields: Vec<UpdateBits>,
+    ) -> Result<SubscriptionGuard, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = TickerPlantCommand::AcquireMarketData {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            fields,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        let guard = SubscriptionGuard {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            sender: self.sender.clone(),
+        };
+
+        // Dropping the guard on error releases the reference taken above
+        rx.await.map_err(not_connected)??;
+
+        Ok(guard)
     }
+
+    /// Trades of `symbol` only, subscribed with the `LastTrade` field alone. The subscription
+    /// is released when the receiver is dropped.
+    pub async fn trades(
+        &self,
+        symbol: &str,
+        exchange: &str,
+    ) -> Result<mpsc::Receiver<LastTrade>, String> {
+        let key = (symbol.to_string(), exchange.to_string());
+
+        self.split_stream(symbol, exchange, UpdateBits::LastTrade, move |message| match message {
+            RithmicMessage::LastTrade(trade) if is_instrument(&trade.symbol, &trade.exchange, &key) => {
+                Some(trade)
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    /// Best bid and offer updates of `symbol` only, subscribed with the `Bbo` field alone. The
+    /// subscription is released when the receiver is dropped.
+    pub async fn quotes(
+        &self,
+        symbol: &str,
+        exchange: &str,
+    ) -> Result<mpsc::Receiver<BestBidOffer>, String> {
+        let key = (symbol.to_string(), exchange.to_string());
+
+        self.split_stream(symbol, exchange, UpdateBits::Bbo, move |message| match message {
+            RithmicMessage::BestBidOffer(bbo) if is_instrument(&bbo.symbol, &bbo.exchange, &key) => {
+                Some(bbo)
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    /// Updates of `symbol` alone on their own broadcast channel, for several consumers of the
+    /// same feed: each `MarketDataFeed::subscribe` gets a receiver with every update. Like the
+    /// plant's channel the feed never waits for its consumers, a receiver more than 1024 updates
+    /// behind skips the oldest ones and gets `RecvError::Lagged`. The subscription is released
+    /// when the feed is dropped, its receivers then get `RecvError::Closed`.
+    pub async fn market_data_feed(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        fields: Vec<UpdateBits>,
+    ) -> Result<MarketDataFeed, String> {
+        // Subscribed first so the snapshot sent on subscribe isn't missed
+        let mut updates = self.subscription_sender.subscribe();
+        let guard = self.subscribe_market_data(symbol, exchange, fields).await?;
+
+        let (feed_sender, _) = broadcast::channel(FEED_BUFFER_SIZE);
+        let (stop_sender, mut stop_receiver) = oneshot::channel::<()>();
+
+        let key = (symbol.to_string(), exchange.to_string());
+        let sender = feed_sender.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let update = tokio::select! {
+                    update = updates.recv() => update,
+                    _ = &mut stop_receiver => break,
+                };
+
+                match update {
+                    Ok(response) => {
+                        if update_instrument(&response.message)
+                            .is_some_and(|(symbol, exchange)| is_instrument(symbol, exchange, &key))
+                        {
+                            // No receiver at the moment is fine, the update is dropped
+                            let _ = sender.send(response);
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(MarketDataFeed {
+            _guard: guard,
+            _stop: stop_sender,
+            sender: feed_sender,
+        })
+    }
+
+    /// Subscribe `field` and forward the updates `select` keeps until the receiver is dropped
+    async fn split_stream<T, F>(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        field: UpdateBits,
+        select: F,
+    ) -> Result<mpsc::Receiver<T>, String>
+    where
+        T: Send + 'static,
+        F: Fn(RithmicMessage) -> Option<T> + Send + 'static,
+    {
+        // Subscribed first so the snapshot sent on subscribe isn't missed
+        let mut updates = self.subscription_sender.subscribe();
+        let guard = self.subscribe_market_data(symbol, exchange, vec![field]).await?;
+
+        let (tx, rx) = mpsc::channel(FEED_BUFFER_SIZE);
+
+        tokio::spawn(async move {
+            // Released when the receiver is dropped or the plant stops
+            let _guard = guard;
+
+            loop {
+                let update = tokio::select! {
+                    update = updates.recv() => update,
+                    _ = tx.closed() => break,
+                };
+
+                match update {
+                    Ok(response) => {
+                        if let Some(item) = select(response.message)
+                            && tx.send(item).await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(rx)
+    }
+
+    /// Unsubscribe every `subscribe_market_data` feed from the gateway while staying logged in,
+    /// heartbeats keep the session alive. Guards stay valid and the feeds are re-subscribed by
+    /// `resume_market_data`.
+    pub async fn pause_market_data(&self) -> Result<(), String> {
+        for (symbol, exchange, fields) in self.set_market_data_paused(true).await {
+            self.update_market_data(&symbol, &exchange, fields, Request::Unsubscribe)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Re-subscribe the feeds of `subscribe_market_data`, including the ones acquired while paused
+    pub async fn resume_market_data(&self) -> Result<(), String> {
+        for (symbol, exchange, fields) in self.set_market_data_paused(false).await {
+            self.update_market_data(&symbol, &exchange, fields, Request::Subscribe)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_market_data_paused(&self, paused: bool) -> Vec<(String, String, Vec<UpdateBits>)> {
+        let (tx, rx) = oneshot::channel();
+
+        let command = TickerPlantCommand::SetMarketDataPaused {
+            paused,
+            response_sender: tx,
+        };
+
+        let _ = self.sender.send(command).await;
+
+        // Nothing is paused or resumed once the plant's actor has stopped
+        rx.await.unwrap_or_default()
+    }
+
+    async fn update_market_data(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        fields: Vec<UpdateBits>,
+        request_type: Request,
+    ) -> Result<RithmicResponse, String> {
+        let (tx, rx) = oneshot::channel::<Result<Vec<RithmicResponse>, String>>();
+
+        let command = TickerPlantCommand::Subscribe {
+            symbol: symbol.to_string(),
+            exchange: exchange.to_string(),
+            fields,
+            request_type,
+            response_sender: tx,
+        };
+
+        self.sender.send(command).await.map_err(not_connected)?;
+
+        Ok(rx.await.map_err(not_connected)??.remove(0))
+    }
+}
+
+/// Returned by `RithmicTickerPlantHandle::subscribe_market_data`, releases the subscription
+/// when dropped
+#[derive(Debug)]
+pub struct SubscriptionGuard {
+    symbol: String,
+    exchange: String,
+    sender: tokio::sync::mpsc::Sender<TickerPlantCommand>,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        let command = TickerPlantCommand::ReleaseMarketData {
+            symbol: std::mem::take(&mut self.symbol),
+            exchange: std::mem::take(&mut self.exchange),
+        };
+
+        // Drop may run outside of a runtime, nothing can be awaited or spawned here. A stopped
+        // plant has nothing left to release.
+        if let Err(TrySendError::Full(TickerPlantCommand::ReleaseMarketData { symbol, exchange })) =
+            self.sender.try_send(command)
+        {
+            event!(
+                Level::WARN,
+                "ticker_plant: command queue full, market data of {} {} not released",
+                symbol,
+                exchange
+            );
+        }
+    }
+}
+
+/// The plant's actor stopped, e.g. its connection was closed or failed
+fn not_connected<E>(_: E) -> String {
+    plant_not_connected("ticker")
+}
+
+/// Returned by `RithmicTickerPlantHandle::market_data_feed`, releases the subscription when
+/// dropped
+#[derive(Debug)]
+pub struct MarketDataFeed {
+    _guard: SubscriptionGuard,
+    /// Stops the forwarding task when dropped
+    _stop: oneshot::Sender<()>,
+    sender: broadcast::Sender<RithmicResponse>,
+}
+
+impl MarketDataFeed {
+    /// A new receiver getting the updates sent from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<RithmicResponse> {
+        self.sender.subscribe()
+    }
+}
+
+/// Check an instrument can be traded before placing an order: its exchange is entitled, its
+/// reference data is cached and its last market mode is open and not halted. Subscribe with
+/// `UpdateBits::MarketMode` for the market mode to be known.
+pub async fn can_trade(
+    ticker: &RithmicTickerPlantHandle,
+    orders: &RithmicOrderPlantHandle,
+    symbol: &str,
+    exchange: &str,
+) -> TradeabilityReport {
+    let mut report = TradeabilityReport::default();
+
+    match orders.is_entitled(exchange).await {
+        Ok(entitled) => {
+            report.entitled = entitled;
+
+            if !entitled {
+                report.reasons.push(format!("not entitled to {}", exchange));
+            }
+        }
+        Err(e) => report.reasons.push(format!("entitlements unknown: {}", e)),
+    }
+
+    report.reference_data_known = ticker.cached_reference_data(symbol, exchange).is_some();
+
+    if !report.reference_data_known {
+        report.reasons.push(format!("no reference data cached for {} {}", symbol, exchange));
+    }
+
+    if let Some(mode) = ticker.market_mode(symbol, exchange) {
+        let market_mode = mode.market_mode.as_deref().unwrap_or_default().to_lowercase();
+        let halt_reason = mode.halt_reason.as_deref().unwrap_or_default();
+
+        let market_open = market_mode == "open";
+        let not_halted = !market_mode.contains("halt") && halt_reason.is_empty();
+
+        if !market_open {
+            report.reasons.push(format!("market is {:?}", market_mode));
+        }
+
+        if !not_halted {
+            let reason = if halt_reason.is_empty() { market_mode.as_str() } else { halt_reason };
+
+            report.reasons.push(format!("trading halted: {}", reason));
+        }
+
+        report.market_open = Some(market_open);
+        report.not_halted = Some(not_halted);
+    }
+
+    report
 }
 
 impl Clone for RithmicTickerPlantHandle {
     fn clone(&self) -> Self {
         RithmicTickerPlantHandle {
+            bbos: self.bbos.clone(),
+            connection_monitor: self.connection_monitor.clone(),
+            frame_capture: self.frame_capture.clone(),
+            margin_rates: self.margin_rates.clone(),
+            market_modes: self.market_modes.clone(),
+            reference_data_cache: self.reference_data_cache.clone(),
             sender: self.sender.clone(),
+            server_clock: self.server_clock.clone(),
             subscription_sender: self.subscription_sender.clone(),
             subscription_receiver: self.subscription_sender.subscribe(),
+            vwaps: self.vwaps.clone(),
         }
     }
 }
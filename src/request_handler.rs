@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 use tracing::{event, Level};
 
 use crate::{api::receiver_api::RithmicResponse, rti::messages::RithmicMessage};
@@ -15,6 +15,10 @@ pub struct RithmicRequest {
 pub struct RithmicRequestHandler {
     handle_map: HashMap<String, oneshot::Sender<Result<Vec<RithmicResponse>, String>>>,
     response_vec_map: HashMap<String, Vec<RithmicResponse>>,
+    /// Requests whose rejection is answered with the response itself rather than an error
+    keeping_rejections: HashSet<String>,
+    /// Updates are being dropped, no receiver is listening
+    updates_unheard: bool,
 }
 
 impl RithmicRequestHandler {
@@ -22,43 +26,131 @@ impl RithmicRequestHandler {
         Self {
             handle_map: HashMap::new(),
             response_vec_map: HashMap::new(),
+            keeping_rejections: HashSet::new(),
+            updates_unheard: false,
         }
     }
 
     pub fn register_request(&mut self, request: RithmicRequest) {
+        self.sweep_cancelled();
+
         self.handle_map
             .insert(request.request_id, request.responder);
     }
 
+    /// Register a request whose rejection is answered with the rejected response, for callers
+    /// that need more of it than the error text, e.g. the code of an order rejection
+    pub fn register_request_keeping_rejections(&mut self, request: RithmicRequest) {
+        self.keeping_rejections.insert(request.request_id.clone());
+        self.register_request(request);
+    }
+
+    /// Drop requests whose caller went away before the response arrived,
+    /// e.g. a timed out future, so they don't pile up
+    fn sweep_cancelled(&mut self) {
+        let response_vec_map = &mut self.response_vec_map;
+        let keeping_rejections = &mut self.keeping_rejections;
+
+        self.handle_map.retain(|request_id, responder| {
+            if responder.is_closed() {
+                response_vec_map.remove(request_id);
+                keeping_rejections.remove(request_id);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Fail every pending request, e.g. when the connection they were sent on is lost
+    pub fn fail_pending(&mut self, error: &str) {
+        self.response_vec_map.clear();
+        self.keeping_rejections.clear();
+
+        for (_, responder) in self.handle_map.drain() {
+            let _ = responder.send(Err(error.to_string()));
+        }
+    }
+
+    /// Route a decoded message: updates go to the plant's update channel, responses to the
+    /// caller waiting for the request their `request_id` belongs to.
+    ///
+    /// Returns true for the first update dropped since a receiver last listened, so the plant
+    /// can apply its `UnheardUpdates` policy once.
+    pub fn dispatch(
+        &mut self,
+        response: RithmicResponse,
+        updates: &broadcast::Sender<RithmicResponse>,
+    ) -> bool {
+        if !response.is_update {
+            self.handle_response(response);
+
+            return false;
+        }
+
+        // Only fails when no handle is listening, the update is dropped
+        let unheard = updates.send(response).is_err();
+
+        if unheard == self.updates_unheard {
+            return false;
+        }
+
+        self.updates_unheard = unheard;
+
+        if unheard {
+            event!(Level::WARN, "no update receiver, dropping updates until one subscribes");
+        } else {
+            event!(Level::INFO, "update receiver subscribed, updates are delivered again");
+        }
+
+        unheard
+    }
+
     pub fn handle_response(&mut self, response: RithmicResponse) {
         match response.message {
             RithmicMessage::ResponseHeartbeat(_) => {}
             _ => {
                 if !response.multi_response {
+                    // A reject can end a multi-response stream, its parts must not be
+                    // mistaken for a complete result
+                    let parts = self.response_vec_map.remove(&response.request_id);
+
                     if let Some(responder) = self.handle_map.remove(&response.request_id) {
-                        responder.send(Ok(vec![response])).unwrap();
+                        let result = match parts {
+                            Some(parts) => Err(truncated_stream_error(&parts, &response)),
+                            None => self.reject_or_answer(response),
+                        };
+
+                        // The caller may have stopped waiting for the response
+                        let _ = responder.send(result);
                     } else {
                         event!(Level::ERROR, "No responder found for response: {:#?}", response);
                     }
                 } else {
                     // If response has more, we store it in a vector and wait for more messages
                     if response.has_more {
+                        // Parts of a cancelled request are not kept
+                        if !self.handle_map.contains_key(&response.request_id) {
+                            return;
+                        }
+
                         self.response_vec_map
                             .entry(response.request_id.clone())
                             .or_default()
                             .push(response);
                     } else if let Some(responder) = self.handle_map.remove(&response.request_id) {
-                        let response_vec = match self.response_vec_map.remove(&response.request_id)
-                        {
+                        let result = match self.response_vec_map.remove(&response.request_id) {
+                            Some(vec) if response.error.is_some() => {
+                                Err(truncated_stream_error(&vec, &response))
+                            }
                             Some(mut vec) => {
                                 vec.push(response);
-                                vec
-                            }
-                            None => {
-                                vec![response]
+                                Ok(vec)
                             }
+                            // The first part can already be a rejection
+                            None => self.reject_or_answer(response),
                         };
-                        responder.send(Ok(response_vec)).unwrap();
+                        let _ = responder.send(result);
                     } else {
                         event!(Level::ERROR, "No responder found for response: {:#?}", response);
                     }
@@ -66,6 +158,29 @@ impl RithmicRequestHandler {
             }
         }
     }
+
+    /// Answer a request with its only response, or with the error it was rejected with
+    fn reject_or_answer(
+        &mut self,
+        response: RithmicResponse,
+    ) -> Result<Vec<RithmicResponse>, String> {
+        let keep_rejection = self.keeping_rejections.remove(&response.request_id);
+
+        match &response.error {
+            Some(error) if !keep_rejection => Err(error.clone()),
+            _ => Ok(vec![response]),
+        }
+    }
+}
+
+/// Error for a multi-response stream that ended with an error after some of its parts arrived
+fn truncated_stream_error(parts: &[RithmicResponse], response: &RithmicResponse) -> String {
+    format!(
+        "request {} failed after {} responses: {}",
+        response.request_id,
+        parts.len(),
+        response.error.as_deref().unwrap_or("rejected")
+    )
 }
 
 impl Default for RithmicRequestHandler {
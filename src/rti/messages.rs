@@ -4,24 +4,31 @@ use super::*;
 #[derive(Debug, Clone)]
 pub enum RithmicMessage {
     AccountPnLPositionUpdate(AccountPnLPositionUpdate),
+    AccountRmsUpdates(AccountRmsUpdates),
     BestBidOffer(BestBidOffer),
     BracketUpdates(BracketUpdates),
     DepthByOrder(DepthByOrder),
+    EndOfDayPrices(EndOfDayPrices),
     ExchangeOrderNotification(ExchangeOrderNotification),
     ForcedLogout(ForcedLogout),
+    FrontMonthContractUpdate(FrontMonthContractUpdate),
     InstrumentPnLPositionUpdate(InstrumentPnLPositionUpdate),
     LastTrade(LastTrade),
+    MarketMode(MarketMode),
     OpenInterest(OpenInterest),
     OrderBook(OrderBook),
     Reject(Reject),
     ResponseAccountList(ResponseAccountList),
     ResponseAccountRmsInfo(ResponseAccountRmsInfo),
+    ResponseAccountRmsUpdates(ResponseAccountRmsUpdates),
     ResponseBracketOrder(ResponseBracketOrder),
     ResponseCancelAllOrders(ResponseCancelAllOrders),
     ResponseCancelOrder(ResponseCancelOrder),
     ResponseExitPosition(ResponseExitPosition),
+    ResponseFrontMonthContract(ResponseFrontMonthContract),
     ResponseGetInstrumentByUnderlying(ResponseGetInstrumentByUnderlying),
     ResponseHeartbeat(ResponseHeartbeat),
+    ResponseListExchangePermissions(ResponseListExchangePermissions),
     ResponseLogin(ResponseLogin),
     ResponseLogout(ResponseLogout),
     ResponseMarketDataUpdate(ResponseMarketDataUpdate),
@@ -32,6 +39,8 @@ pub enum RithmicMessage {
     ResponseProductCodes(ResponseProductCodes),
     ResponseProductRmsInfo(ResponseProductRmsInfo),
     ResponseReferenceData(ResponseReferenceData),
+    ResponseReplayExecutions(ResponseReplayExecutions),
+    ResponseResumeBars(ResponseResumeBars),
     ResponseRithmicSystemInfo(ResponseRithmicSystemInfo),
     ResponseRithmicSystemGatewayInfo(ResponseRithmicSystemGatewayInfo),
     ResponseSearchSymbols(ResponseSearchSymbols),
@@ -53,6 +62,7 @@ pub enum RithmicMessage {
     ResponseUpdateTargetBracketLevel(ResponseUpdateTargetBracketLevel),
     ResponseVolumeProfileMinuteBars(ResponseVolumeProfileMinuteBars),
     RithmicOrderNotification(RithmicOrderNotification),
+    SymbolMarginRate(SymbolMarginRate),
     TickBar(TickBar),
     TimeBar(TimeBar),
 }
@@ -1,18 +1,25 @@
+use std::collections::HashMap;
 use std::env;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::anyhow;
 use async_trait::async_trait;
 use base64::Engine;
 use base64::prelude::BASE64_STANDARD;
 use bytes::Bytes;
 use http::{Request, Uri};
-use http::header::PROXY_AUTHORIZATION;
+use http::header::{HeaderName, HeaderValue, PROXY_AUTHORIZATION};
 use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::{RecvError, TryRecvError}};
+use tokio::sync::oneshot;
 use tokio::time::{interval_at, Instant, Interval};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{event, Level};
 use tokio_tungstenite::tungstenite::{Error, Message};
 use tungstenite::client::IntoClientRequest;
 
+use crate::api::receiver_api::RithmicResponse;
+
 pub trait RithmicStream {
     type Handle;
 
@@ -35,8 +42,282 @@ pub fn get_heartbeat_interval() -> Interval {
     interval_at(start_offset, heartbeat_interval)
 }
 
+/// Wait for the first update matching `predicate`, skipping the others.
+/// Lagging is not an error here, the skipped updates just can't match.
+pub async fn wait_for_update<F>(
+    receiver: &mut broadcast::Receiver<RithmicResponse>,
+    predicate: F,
+    timeout: Duration,
+) -> Result<RithmicResponse, String>
+where
+    F: Fn(&RithmicResponse) -> bool,
+{
+    let wait = async {
+        loop {
+            match receiver.recv().await {
+                Ok(response) if predicate(&response) => return Ok(response),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Err("plant stopped".to_string()),
+            }
+        }
+    };
+
+    tokio::time::timeout(timeout, wait)
+        .await
+        .map_err(|_| format!("no matching update after {:?}", timeout))?
+}
+
+/// Updates already received by `receiver`, e.g. the notifications the server replays before
+/// answering a request. Fails if the receiver lagged, the updates it skipped are lost and the
+/// result would be silently incomplete.
+pub fn drain_updates(
+    receiver: &mut broadcast::Receiver<RithmicResponse>,
+) -> Result<Vec<RithmicResponse>, String> {
+    let mut updates = vec![];
+
+    loop {
+        match receiver.try_recv() {
+            Ok(update) => updates.push(update),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return Ok(updates),
+            Err(TryRecvError::Lagged(skipped)) => {
+                return Err(format!(
+                    "{} replayed updates were dropped, increase update_buffer_size",
+                    skipped
+                ));
+            }
+        }
+    }
+}
+
+/// End of the error a plant handle returns once the plant's actor has stopped
+pub const PLANT_NOT_CONNECTED: &str = "plant not connected";
+
+/// Whether an error returned by a plant handle means the plant's actor has stopped, e.g. its
+/// connection was closed or failed, so the caller can reconnect it
+pub fn is_plant_not_connected(error: &str) -> bool {
+    error.ends_with(PLANT_NOT_CONNECTED)
+}
+
+/// The error a plant handle returns once the plant's actor has stopped
+pub fn plant_not_connected(plant: &str) -> String {
+    format!("{} {}", plant, PLANT_NOT_CONNECTED)
+}
+
+/// How long `disconnect` waits for the feeds of a plant to be unsubscribed before logging out
+pub const UNSUBSCRIBE_ON_DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pending responses to the unsubscribe requests a plant sends before logging out
+pub type UnsubscribeResponses = Vec<oneshot::Receiver<Result<Vec<RithmicResponse>, String>>>;
+
+/// Wait for the responses to the unsubscribe requests sent before logging out, for up to
+/// `UNSUBSCRIBE_ON_DISCONNECT_TIMEOUT`. Failures are logged, the plant logs out anyway.
+pub async fn await_unsubscribes(
+    source: &str,
+    responses: UnsubscribeResponses,
+) {
+    let wait = async {
+        for response in responses {
+            if let Ok(Err(e)) = response.await {
+                event!(Level::WARN, "{}: unsubscribe failed: {}", source, e);
+            }
+        }
+    };
+
+    if tokio::time::timeout(UNSUBSCRIBE_ON_DISCONNECT_TIMEOUT, wait).await.is_err() {
+        event!(
+            Level::WARN,
+            "{}: feeds not unsubscribed after {:?}, logging out anyway",
+            source,
+            UNSUBSCRIBE_ON_DISCONNECT_TIMEOUT
+        );
+    }
+}
+
+/// Tracks the time since the last user request, checked on each heartbeat tick
+#[derive(Debug)]
+pub struct IdleTimer {
+    last_activity: Instant,
+    limit: Option<Duration>,
+}
+
+impl IdleTimer {
+    pub fn new(limit_secs: u64) -> Self {
+        IdleTimer {
+            last_activity: Instant::now(),
+            limit: (limit_secs > 0).then(|| Duration::from_secs(limit_secs)),
+        }
+    }
+
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn expired(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.last_activity.elapsed() >= limit)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ServerClockState {
+    heartbeat_sent_usecs: Option<i64>,
+    offset_usecs: Option<i64>,
+}
+
+/// Estimated offset between the gateway clock and the local one, updated from the time stamped
+/// on heartbeat responses. The gateway is assumed to stamp the response halfway between the
+/// request being sent and the response being received.
+#[derive(Debug, Clone, Default)]
+pub struct ServerClock {
+    state: Arc<Mutex<ServerClockState>>,
+}
+
+impl ServerClock {
+    pub fn heartbeat_sent(&self) {
+        self.state.lock().unwrap().heartbeat_sent_usecs = Some(local_usecs());
+    }
+
+    pub fn heartbeat_received(&self, ssboe: Option<i32>, usecs: Option<i32>) {
+        let Some(ssboe) = ssboe else {
+            return;
+        };
+
+        let server_usecs = ssboe as i64 * 1_000_000 + usecs.unwrap_or_default() as i64;
+        let received_usecs = local_usecs();
+
+        let mut state = self.state.lock().unwrap();
+
+        let local_usecs = match state.heartbeat_sent_usecs.take() {
+            Some(sent_usecs) => sent_usecs + (received_usecs - sent_usecs) / 2,
+            None => received_usecs,
+        };
+
+        state.offset_usecs = Some(server_usecs - local_usecs);
+    }
+
+    /// Gateway time minus local time in microseconds, positive when the gateway clock is ahead.
+    /// None until a heartbeat response carrying a time has been received.
+    pub fn offset_usecs(&self) -> Option<i64> {
+        self.state.lock().unwrap().offset_usecs
+    }
+}
+
+/// Connection health of a plant since it was created
+#[derive(Debug, Clone)]
+pub struct ConnectionStats {
+    /// When the current connection was opened
+    pub connected_since: SystemTime,
+    pub reconnect_count: u64,
+    /// Last connection or server error, kept after a successful reconnect
+    pub last_error: Option<String>,
+}
+
+/// Shares the `ConnectionStats` of a plant with its handles, updated by the plant
+#[derive(Debug, Clone)]
+pub struct ConnectionMonitor {
+    stats: Arc<Mutex<ConnectionStats>>,
+}
+
+impl Default for ConnectionMonitor {
+    fn default() -> Self {
+        ConnectionMonitor {
+            stats: Arc::new(Mutex::new(ConnectionStats {
+                connected_since: SystemTime::now(),
+                reconnect_count: 0,
+                last_error: None,
+            })),
+        }
+    }
+}
+
+impl ConnectionMonitor {
+    pub fn reconnected(&self) {
+        let mut stats = self.stats.lock().unwrap();
+
+        stats.connected_since = SystemTime::now();
+        stats.reconnect_count += 1;
+    }
+
+    pub fn error(&self, error: impl ToString) {
+        self.stats.lock().unwrap().last_error = Some(error.to_string());
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+fn local_usecs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_micros() as i64)
+        .unwrap_or_default()
+}
+
+/// Normalize a gateway url: `wss://` is assumed when the scheme is missing, `http(s)://` is
+/// mapped to `ws(s)://` and the default port for the scheme is added when missing.
+pub fn normalize_url(url: &str) -> Result<Uri, anyhow::Error> {
+    let url = url.trim();
+
+    let (scheme, rest) = match url.split_once("://") {
+        Some(("wss", rest)) | Some(("https", rest)) => ("wss", rest),
+        Some(("ws", rest)) | Some(("http", rest)) => ("ws", rest),
+        Some((scheme, _)) => {
+            return Err(anyhow!("invalid gateway url {}: unsupported scheme {}", url, scheme));
+        }
+        None => ("wss", url),
+    };
+
+    let uri: Uri = format!("{}://{}", scheme, rest)
+        .parse()
+        .map_err(|e| anyhow!("invalid gateway url {}: {}", url, e))?;
+
+    let host = match uri.host() {
+        Some(host) if !host.is_empty() => host,
+        _ => return Err(anyhow!("invalid gateway url {}: missing host", url)),
+    };
+
+    let port = uri
+        .port_u16()
+        .unwrap_or(if scheme == "wss" { 443 } else { 80 });
+
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+    format!("{}://{}:{}{}", scheme, host, port, path)
+        .parse()
+        .map_err(|e| anyhow!("invalid gateway url {}: {}", url, e))
+}
+
 pub async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, anyhow::Error> {
-    let ws_uri: Uri = url.parse()?;
+    connect_with_request(normalize_url(url)?.into_client_request()?).await
+}
+
+/// Websocket handshake request for a gateway url with extra headers, e.g. the `Authorization`
+/// required by an auth proxy in front of the gateway
+pub fn gateway_request(
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> Result<Request<()>, anyhow::Error> {
+    let mut request = normalize_url(url)?.into_client_request()?;
+
+    for (name, value) in headers {
+        request.headers_mut().insert(
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| anyhow!("invalid header name {}: {}", name, e))?,
+            HeaderValue::from_str(value)
+                .map_err(|e| anyhow!("invalid value for header {}: {}", name, e))?,
+        );
+    }
+
+    Ok(request)
+}
+
+/// Connect with a prepared handshake request, see `gateway_request`. The request uri is used
+/// as is, it must be a complete `ws(s)://host:port` url.
+pub async fn connect_with_request(
+    request: Request<()>,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, anyhow::Error> {
+    let ws_uri: Uri = request.uri().clone();
 
     if let Ok(proxy_url_str) = env::var("HTTPS_PROXY") {
         let proxy_uri: hyper::Uri = proxy_url_str.parse()?;
@@ -56,9 +337,9 @@ pub async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStre
                 request_builder = request_builder.header(PROXY_AUTHORIZATION, auth);
             }
         }
-        let request = request_builder.body(http_body_util::Empty::<Bytes>::new())?;
+        let connect_request = request_builder.body(http_body_util::Empty::<Bytes>::new())?;
 
-        let res = request_sender.send_request(request).await?;
+        let res = request_sender.send_request(connect_request).await?;
 
         if !res.status().is_success() {
             return Err(anyhow!(
@@ -74,10 +355,37 @@ pub async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStre
             .into_inner();
 
         // CryptoProvider::install_default();
-        let ws_stream = tokio_tungstenite::client_async_tls(ws_uri.into_client_request()?, tcp).await?.0;
+        let ws_stream = tokio_tungstenite::client_async_tls(request, tcp).await?.0;
         Ok(ws_stream)
     } else {
-        let ws_stream = tokio_tungstenite::connect_async(ws_uri.into_client_request()?).await?.0;
+        let ws_stream = tokio_tungstenite::connect_async(request).await?.0;
         Ok(ws_stream)
     }
+}
+
+/// Connect to the first reachable gateway of `urls`, tried in order, adding `headers` to the
+/// handshake. Returns the stream and the url it is connected to.
+pub async fn connect_with_failover(
+    urls: &[String],
+    headers: &HashMap<String, String>,
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, String), anyhow::Error> {
+    let mut last_error = anyhow!("no gateway url to connect to");
+
+    for url in urls {
+        let connection = match gateway_request(url, headers) {
+            Ok(request) => connect_with_request(request).await,
+            Err(e) => Err(e),
+        };
+
+        match connection {
+            Ok(ws_stream) => return Ok((ws_stream, url.clone())),
+            Err(e) => {
+                event!(Level::WARN, "connection to gateway {} failed: {}", url, e);
+
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
 }
\ No newline at end of file
@@ -0,0 +1,49 @@
+//! Checks tick bar replay requests carry the bar size, without a network.
+
+use prost::Message;
+
+use rithmic_client::{
+    api::{
+        RithmicConnectionInfo, rithmic_command_types::validate_bar_type_specifier,
+        sender_api::RithmicSenderApi,
+    },
+    rti::{RequestTickBarReplay, request_tick_bar_replay},
+};
+
+use request_tick_bar_replay::{BarSubType, BarType, Direction, TimeOrder};
+
+#[test]
+fn volume_bar_replay_is_encoded_with_its_size() {
+    let mut sender_api = RithmicSenderApi::new(&RithmicConnectionInfo::default());
+
+    let (buf, _id) = sender_api.request_tick_bar_replay(
+        "ESZ6",
+        "CME",
+        BarType::VolumeBar,
+        BarSubType::Regular,
+        "500",
+        1_790_000_000,
+        1_790_003_600,
+        Direction::First,
+        TimeOrder::Forwards,
+    );
+
+    let req = RequestTickBarReplay::decode(&buf[4..]).unwrap();
+
+    assert_eq!(req.bar_type(), BarType::VolumeBar);
+    assert_eq!(req.bar_type_specifier.as_deref(), Some("500"));
+}
+
+#[test]
+fn bar_size_must_be_positive() {
+    assert!(validate_bar_type_specifier(BarType::VolumeBar, "500").is_ok());
+    assert!(validate_bar_type_specifier(BarType::RangeBar, "4").is_ok());
+
+    for specifier in ["", "0", "-5", "500 contracts"] {
+        assert!(
+            validate_bar_type_specifier(BarType::VolumeBar, specifier).is_err(),
+            "{:?}",
+            specifier
+        );
+    }
+}
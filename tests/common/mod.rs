@@ -0,0 +1,13 @@
+//! Helpers shared by the tests.
+
+use bytes::{BufMut, Bytes, BytesMut};
+use prost::Message;
+
+/// `message` as the server sends it: a 4 byte length header followed by the encoded message
+pub fn frame(message: impl Message) -> Bytes {
+    let mut data = BytesMut::new();
+    data.put_u32(message.encoded_len() as u32);
+    data.extend_from_slice(&message.encode_to_vec());
+
+    data.freeze()
+}
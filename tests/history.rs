@@ -0,0 +1,162 @@
+//! Checks chunked time bar replays resume the replays the server stops at its bar limit,
+//! against a local websocket server standing in for the gateway.
+
+mod common;
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use prost::Message as ProstMessage;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{WebSocketStream, tungstenite::Message};
+
+use rithmic_client::{
+    api::{
+        RithmicConnectionInfo,
+        rithmic_command_types::RithmicTimeBarRange,
+        templates::{
+            REQUEST_RESUME_BARS, REQUEST_TIME_BAR_REPLAY, RESPONSE_RESUME_BARS,
+            RESPONSE_TIME_BAR_REPLAY,
+        },
+    },
+    plants::history_plant::RithmicHistoryPlant,
+    rti::{
+        MessageType, RequestResumeBars, RequestTimeBarReplay, ResponseResumeBars,
+        ResponseTimeBarReplay, request_time_bar_replay::BarType,
+    },
+    ws::RithmicStream,
+};
+
+use common::frame;
+
+/// Bars the server sends per replay before asking for a resume
+const BAR_LIMIT: i32 = 3;
+
+/// Send one bar per second from `from` to `finish`, stopping at `BAR_LIMIT` bars with a
+/// request key to resume from
+async fn send_bars(
+    ws: &mut WebSocketStream<tokio::net::TcpStream>,
+    user_msg: Vec<String>,
+    from: i32,
+    finish: i32,
+) {
+    let last = finish.min(from + BAR_LIMIT - 1);
+
+    for marker in from..=last {
+        let bar = frame(ResponseTimeBarReplay {
+            template_id: RESPONSE_TIME_BAR_REPLAY,
+            user_msg: user_msg.clone(),
+            rq_handler_rp_code: vec!["0".to_string()],
+            marker: Some(marker),
+            ..ResponseTimeBarReplay::default()
+        });
+
+        ws.send(Message::Binary(bar)).await.unwrap();
+    }
+
+    let end = frame(ResponseTimeBarReplay {
+        template_id: RESPONSE_TIME_BAR_REPLAY,
+        user_msg,
+        rp_code: vec!["0".to_string()],
+        request_key: (last < finish).then(|| format!("{}:{}", last + 1, finish)),
+        ..ResponseTimeBarReplay::default()
+    });
+
+    ws.send(Message::Binary(end)).await.unwrap();
+}
+
+/// Answer time bar replays and their resumes, reporting the template id of each request
+async fn serve(listener: TcpListener, requests: mpsc::UnboundedSender<i32>) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+    while let Some(Ok(Message::Binary(data))) = ws.next().await {
+        let template_id = MessageType::decode(&data[4..]).unwrap().template_id;
+        let _ = requests.send(template_id);
+
+        match template_id {
+            REQUEST_TIME_BAR_REPLAY => {
+                let req = RequestTimeBarReplay::decode(&data[4..]).unwrap();
+
+                send_bars(
+                    &mut ws,
+                    req.user_msg,
+                    req.start_index.unwrap(),
+                    req.finish_index.unwrap(),
+                )
+                .await;
+            }
+            REQUEST_RESUME_BARS => {
+                let req = RequestResumeBars::decode(&data[4..]).unwrap();
+                let key = req.request_key.unwrap();
+                let (from, finish) = key.split_once(':').unwrap();
+
+                let ack = frame(ResponseResumeBars {
+                    template_id: RESPONSE_RESUME_BARS,
+                    user_msg: req.user_msg.clone(),
+                    rp_code: vec!["0".to_string()],
+                });
+
+                ws.send(Message::Binary(ack)).await.unwrap();
+
+                send_bars(
+                    &mut ws,
+                    req.user_msg,
+                    from.parse().unwrap(),
+                    finish.parse().unwrap(),
+                )
+                .await;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[tokio::test]
+async fn chunked_replay_resumes_a_chunk_stopped_at_the_bar_limit() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let (requests_tx, mut requests) = mpsc::unbounded_channel();
+    tokio::spawn(serve(listener, requests_tx));
+
+    let plant = RithmicHistoryPlant::try_new(&RithmicConnectionInfo {
+        url,
+        ..RithmicConnectionInfo::default()
+    })
+    .await
+    .unwrap();
+
+    let handle = plant.get_handle();
+
+    let range = RithmicTimeBarRange {
+        symbol: "ESZ6".to_string(),
+        exchange: "CME".to_string(),
+        bar_type: BarType::SecondBar,
+        bar_type_period: 1,
+        start_index: 0,
+        finish_index: 5,
+    };
+
+    // Chunks of 0..=3 and 4..=5, the first one goes over the limit
+    let markers: Vec<i32> = handle
+        .get_historical_time_bar_chunked(range, Duration::from_secs(4))
+        .unwrap()
+        .map(|bar| bar.unwrap().marker.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(markers, vec![0, 1, 2, 3, 4, 5]);
+
+    let mut seen = vec![];
+
+    while let Ok(template_id) = requests.try_recv() {
+        seen.push(template_id);
+    }
+
+    assert_eq!(
+        seen,
+        vec![REQUEST_TIME_BAR_REPLAY, REQUEST_RESUME_BARS, REQUEST_TIME_BAR_REPLAY]
+    );
+}
@@ -0,0 +1,282 @@
+//! Checks the order types are encoded with the prices the protocol expects, without a network.
+
+mod common;
+
+use prost::Message;
+
+use rithmic_client::{
+    api::{
+        RithmicConnectionInfo,
+        receiver_api::RithmicReceiverApi,
+        rithmic_command_types::{
+            OrderAck, RithmicFill, RithmicModifyOrder, RithmicOrder, total_unfilled_size,
+        },
+        sender_api::RithmicSenderApi,
+        templates::RESPONSE_NEW_ORDER,
+    },
+    rti::{
+        ExchangeOrderNotification, RequestNewOrder, ResponseNewOrder, RithmicOrderNotification,
+        exchange_order_notification, request_modify_order, request_new_order,
+        rithmic_order_notification,
+    },
+};
+
+use common::frame;
+
+fn order(ordertype: request_new_order::PriceType, trigger_price: Option<f64>) -> RithmicOrder {
+    RithmicOrder {
+        account_id: None,
+        action: request_new_order::TransactionType::Buy,
+        auto: None,
+        cancel_at_ssboe: None,
+        correlation: None,
+        duration: None,
+        exchange: "CME".to_string(),
+        localid: "1".to_string(),
+        ordertype,
+        price: 5000.25,
+        qty: 1,
+        symbol: "ESZ6".to_string(),
+        trigger_price,
+    }
+}
+
+fn modify(
+    ordertype: request_modify_order::PriceType,
+    price: Option<f64>,
+    trigger_price: Option<f64>,
+) -> RithmicModifyOrder {
+    RithmicModifyOrder {
+        id: "1".to_string(),
+        auto: None,
+        exchange: "CME".to_string(),
+        symbol: "ESZ6".to_string(),
+        qty: 1,
+        price,
+        trigger_price,
+        ordertype: ordertype.into(),
+    }
+}
+
+#[test]
+fn if_touched_orders_require_a_trigger_price() {
+    use request_new_order::PriceType;
+
+    for ordertype in [PriceType::MarketIfTouched, PriceType::LimitIfTouched] {
+        assert!(order(ordertype, None).validate().is_err());
+        assert!(order(ordertype, Some(5001.0)).validate().is_ok());
+    }
+
+    assert!(order(PriceType::Limit, Some(5001.0)).validate().is_err());
+}
+
+#[test]
+fn if_touched_order_is_encoded_with_its_trigger_price() {
+    let mut sender_api = RithmicSenderApi::new(&RithmicConnectionInfo::default());
+
+    let (buf, _id) = sender_api.request_new_order(&order(
+        request_new_order::PriceType::MarketIfTouched,
+        Some(4990.0),
+    ));
+
+    let req = RequestNewOrder::decode(&buf[4..]).unwrap();
+
+    assert_eq!(req.price_type(), request_new_order::PriceType::MarketIfTouched);
+    assert_eq!(req.trigger_price, Some(4990.0));
+}
+
+#[test]
+fn if_touched_modifies_check_their_prices() {
+    use request_modify_order::PriceType;
+
+    assert!(modify(PriceType::MarketIfTouched, None, Some(4990.0)).validate().is_ok());
+    assert!(modify(PriceType::MarketIfTouched, Some(5000.0), Some(4990.0)).validate().is_err());
+    assert!(modify(PriceType::LimitIfTouched, Some(5000.0), Some(4990.0)).validate().is_ok());
+    assert!(modify(PriceType::LimitIfTouched, Some(5000.0), None).validate().is_err());
+}
+
+#[test]
+fn good_till_date_order_is_encoded_as_gtc_with_its_cancel_time() {
+    use request_new_order::Duration;
+
+    let mut sender_api = RithmicSenderApi::new(&RithmicConnectionInfo::default());
+
+    let mut gtd_order = order(request_new_order::PriceType::Limit, None);
+    gtd_order.cancel_at_ssboe = Some(1_798_761_600);
+
+    assert!(gtd_order.validate().is_ok());
+
+    let (buf, _id) = sender_api.request_new_order(&gtd_order);
+    let req = RequestNewOrder::decode(&buf[4..]).unwrap();
+
+    assert_eq!(req.duration(), Duration::Gtc);
+    assert_eq!(req.cancel_at_ssboe, Some(1_798_761_600));
+
+    for duration in [Duration::Day, Duration::Ioc, Duration::Fok] {
+        gtd_order.duration = Some(duration);
+
+        assert!(gtd_order.validate().is_err(), "{:?}", duration);
+    }
+
+    gtd_order.duration = Some(Duration::Gtc);
+    assert!(gtd_order.validate().is_ok());
+}
+
+#[test]
+fn order_account_overrides_the_selected_account() {
+    let mut sender_api = RithmicSenderApi::new(&RithmicConnectionInfo::default());
+    sender_api.set_account("FCM", "IB", "ACCOUNT");
+
+    let mut sub_account_order = order(request_new_order::PriceType::Limit, None);
+    sub_account_order.account_id = Some("ACCOUNT-STRAT1".to_string());
+
+    for (order, account_id) in [
+        (order(request_new_order::PriceType::Limit, None), "ACCOUNT"),
+        (sub_account_order, "ACCOUNT-STRAT1"),
+    ] {
+        let (buf, _id) = sender_api.request_new_order(&order);
+        let req = RequestNewOrder::decode(&buf[4..]).unwrap();
+
+        assert_eq!(req.account_id.as_deref(), Some(account_id));
+    }
+}
+
+#[test]
+fn order_correlation_is_echoed_on_the_response() {
+    let mut sender_api = RithmicSenderApi::new(&RithmicConnectionInfo::default());
+
+    let mut tagged_order = order(request_new_order::PriceType::Limit, None);
+    tagged_order.correlation = Some("strategy-7".to_string());
+
+    let (buf, id) = sender_api.request_new_order(&tagged_order);
+    let req = RequestNewOrder::decode(&buf[4..]).unwrap();
+
+    assert_eq!(req.user_msg, vec![id.clone(), "strategy-7".to_string()]);
+
+    let resp = ResponseNewOrder {
+        template_id: RESPONSE_NEW_ORDER,
+        user_msg: req.user_msg,
+        rp_code: vec!["0".to_string()],
+        ..ResponseNewOrder::default()
+    };
+
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    let response = receiver_api.buf_to_message(frame(resp)).unwrap();
+
+    assert_eq!(response.request_id, id);
+    assert_eq!(response.correlation(), Some("strategy-7"));
+}
+
+fn fill(fill_size: i32, total_fill_size: i32, quantity: Option<i32>) -> ExchangeOrderNotification {
+    ExchangeOrderNotification {
+        report_type: Some("fill".to_string()),
+        basket_id: Some("B1".to_string()),
+        fill_price: Some(5000.25),
+        fill_size: Some(fill_size),
+        total_fill_size: Some(total_fill_size),
+        quantity,
+        ..ExchangeOrderNotification::default()
+    }
+}
+
+#[test]
+fn partial_fills_carry_their_size_and_the_running_totals() {
+    let fills: Vec<RithmicFill> = [fill(1, 1, Some(5)), fill(3, 4, Some(5)), fill(1, 5, Some(5))]
+        .iter()
+        .map(|notification| RithmicFill::from_notification(notification).unwrap())
+        .collect();
+
+    let summary: Vec<(i32, i32, Option<i32>, Option<bool>)> = fills
+        .iter()
+        .map(|fill| {
+            (fill.fill_size, fill.total_fill_size, fill.total_unfilled_size, fill.is_partial())
+        })
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            (1, 1, Some(4), Some(true)),
+            (3, 4, Some(1), Some(true)),
+            (1, 5, Some(0), Some(false)),
+        ]
+    );
+    assert_eq!(fills.iter().map(|fill| fill.fill_size).sum::<i32>(), 5);
+
+    // Without the unfilled size nor the order quantity the rest of the order is unknown
+    let fill = RithmicFill::from_notification(&fill(1, 1, None)).unwrap();
+
+    assert_eq!(fill.total_unfilled_size, None);
+    assert_eq!(fill.is_partial(), None);
+}
+
+#[test]
+fn last_fill_leaves_nothing_unfilled_with_or_without_the_unfilled_size() {
+    // Sent by the gateway
+    let last_fill = ExchangeOrderNotification {
+        total_unfilled_size: Some(0),
+        ..fill(2, 5, None)
+    };
+
+    assert_eq!(total_unfilled_size(&last_fill), Some(0));
+
+    // Left out, worked out from the order quantity
+    assert_eq!(total_unfilled_size(&fill(2, 5, Some(5))), Some(0));
+    assert_eq!(total_unfilled_size(&fill(2, 3, Some(5))), Some(2));
+
+    assert_eq!(total_unfilled_size(&fill(2, 5, None)), None);
+}
+
+fn order_notification(
+    notify_type: rithmic_order_notification::NotifyType,
+) -> RithmicOrderNotification {
+    RithmicOrderNotification {
+        basket_id: Some("B1".to_string()),
+        notify_type: Some(notify_type.into()),
+        ..RithmicOrderNotification::default()
+    }
+}
+
+#[test]
+fn order_ack_follows_the_order_notifications() {
+    use rithmic_order_notification::NotifyType;
+
+    let accepted = Some(OrderAck::Accepted {
+        basket_id: "B1".to_string(),
+    });
+
+    assert_eq!(OrderAck::from_notification(&order_notification(NotifyType::OpenPending)), None);
+    assert_eq!(OrderAck::from_notification(&order_notification(NotifyType::Open)), accepted);
+
+    // A market order filled right away completes without being reported open
+    let filled = RithmicOrderNotification {
+        completion_reason: Some("Fill".to_string()),
+        total_fill_size: Some(1),
+        ..order_notification(NotifyType::Complete)
+    };
+
+    assert_eq!(OrderAck::from_notification(&filled), accepted);
+
+    let reject = ExchangeOrderNotification {
+        basket_id: Some("B1".to_string()),
+        notify_type: Some(exchange_order_notification::NotifyType::Reject.into()),
+        text: Some("price outside of limits".to_string()),
+        ..ExchangeOrderNotification::default()
+    };
+
+    assert_eq!(
+        OrderAck::from_exchange_notification(&reject),
+        Some(OrderAck::Rejected {
+            basket_id: Some("B1".to_string()),
+            reason: "price outside of limits".to_string(),
+        })
+    );
+
+    // Other exchange notifications don't tell whether the order was accepted
+    assert_eq!(OrderAck::from_exchange_notification(&fill(1, 1, Some(1))), None);
+}
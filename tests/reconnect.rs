@@ -0,0 +1,173 @@
+//! Checks the order plant survives the loss of its connection and re-issues its subscriptions
+//! on reconnect, against a local websocket server standing in for the gateway.
+
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use prost::Message as ProstMessage;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+use rithmic_client::{
+    api::{
+        RithmicConnectionInfo,
+        templates::{
+            REQUEST_ACCOUNT_LIST, REQUEST_LOGIN, REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES,
+            RESPONSE_ACCOUNT_LIST, RESPONSE_LOGIN, RESPONSE_SUBSCRIBE_FOR_ORDER_UPDATES,
+        },
+    },
+    plants::order_plant::{CONNECTION_LOST, RithmicOrderPlant},
+    rti::{
+        MessageType, RequestAccountList, RequestLogin, RequestSubscribeForOrderUpdates,
+        ResponseAccountList, ResponseLogin, ResponseSubscribeForOrderUpdates,
+    },
+    ws::RithmicStream,
+};
+
+use common::frame;
+
+/// Answer login, order update subscription and account list requests, reporting the template
+/// id of each request with the number of the connection it arrived on. The first connection
+/// leaves account lists unanswered and is closed by the server on the `close_on`th of them.
+async fn serve(
+    listener: TcpListener,
+    requests: mpsc::UnboundedSender<(usize, i32)>,
+    close_on: usize,
+) {
+    for connection in 1..=2 {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let mut account_lists = 0;
+
+        while let Some(Ok(Message::Binary(data))) = ws.next().await {
+            let template_id = MessageType::decode(&data[4..]).unwrap().template_id;
+            let _ = requests.send((connection, template_id));
+
+            if connection == 1 && template_id == REQUEST_ACCOUNT_LIST {
+                account_lists += 1;
+
+                if account_lists == close_on {
+                    let _ = ws.close(None).await;
+
+                    break;
+                }
+
+                continue;
+            }
+
+            let reply = match template_id {
+                REQUEST_LOGIN => frame(ResponseLogin {
+                    template_id: RESPONSE_LOGIN,
+                    user_msg: RequestLogin::decode(&data[4..]).unwrap().user_msg,
+                    rp_code: vec!["0".to_string()],
+                    ..ResponseLogin::default()
+                }),
+                REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES => frame(ResponseSubscribeForOrderUpdates {
+                    template_id: RESPONSE_SUBSCRIBE_FOR_ORDER_UPDATES,
+                    user_msg: RequestSubscribeForOrderUpdates::decode(&data[4..]).unwrap().user_msg,
+                    rp_code: vec!["0".to_string()],
+                }),
+                REQUEST_ACCOUNT_LIST => frame(ResponseAccountList {
+                    template_id: RESPONSE_ACCOUNT_LIST,
+                    user_msg: RequestAccountList::decode(&data[4..]).unwrap().user_msg,
+                    rp_code: vec!["0".to_string()],
+                    ..ResponseAccountList::default()
+                }),
+                _ => continue,
+            };
+
+            ws.send(Message::Binary(reply)).await.unwrap();
+        }
+    }
+}
+
+/// Start the server and connect an order plant to it
+async fn connect(
+    close_on: usize,
+    read_retries: usize,
+) -> (RithmicOrderPlant, mpsc::UnboundedReceiver<(usize, i32)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let (requests_tx, requests) = mpsc::unbounded_channel();
+    tokio::spawn(serve(listener, requests_tx, close_on));
+
+    let plant = RithmicOrderPlant::try_new(&RithmicConnectionInfo {
+        url,
+        read_retries,
+        ..RithmicConnectionInfo::default()
+    })
+    .await
+    .unwrap();
+
+    (plant, requests)
+}
+
+fn received(requests: &mut mpsc::UnboundedReceiver<(usize, i32)>) -> Vec<(usize, i32)> {
+    let mut seen = vec![];
+
+    while let Ok(request) = requests.try_recv() {
+        seen.push(request);
+    }
+
+    seen
+}
+
+#[tokio::test]
+async fn reconnect_resubscribes_order_updates() {
+    let (plant, mut requests) = connect(1, 0).await;
+
+    let handle = plant.get_handle();
+    handle.select_account("fcm", "ib", "account").await;
+
+    handle.login().await.unwrap();
+    handle.subscribe_order_updates().await.unwrap();
+
+    // The server drops the connection on this read, it fails instead of panicking
+    let error = handle.list_accounts("fcm", "ib").await.unwrap_err();
+    assert!(error.contains(CONNECTION_LOST), "{}", error);
+
+    // So does a read sent once the connection is known to be lost
+    let error = handle.list_accounts("fcm", "ib").await.unwrap_err();
+    assert!(error.contains(CONNECTION_LOST), "{}", error);
+
+    handle.reconnect().await.unwrap();
+
+    assert_eq!(
+        received(&mut requests),
+        vec![
+            (1, REQUEST_LOGIN),
+            (1, REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES),
+            (1, REQUEST_ACCOUNT_LIST),
+            (2, REQUEST_LOGIN),
+            (2, REQUEST_SUBSCRIBE_FOR_ORDER_UPDATES),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn concurrent_reads_share_one_reconnect() {
+    let (plant, mut requests) = connect(2, 1).await;
+
+    let handle = plant.get_handle();
+    handle.login().await.unwrap();
+
+    let (first, second) = tokio::join!(
+        handle.list_accounts("fcm", "ib"),
+        handle.list_accounts("fcm", "ib"),
+    );
+
+    first.unwrap();
+    second.unwrap();
+
+    assert_eq!(handle.connection_stats().reconnect_count, 1);
+
+    let logins: Vec<usize> = received(&mut requests)
+        .into_iter()
+        .filter(|(_, template_id)| *template_id == REQUEST_LOGIN)
+        .map(|(connection, _)| connection)
+        .collect();
+
+    assert_eq!(logins, vec![1, 2]);
+}
@@ -0,0 +1,34 @@
+//! Checks the contract expiration is read from the reference data, without a network.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use rithmic_client::{api::rithmic_command_types::ExpirationDate, rti::ResponseReferenceData};
+
+#[test]
+fn expiration_date_is_parsed_from_the_reference_data() {
+    let reference_data = ResponseReferenceData {
+        expiration_date: Some("20261218".to_string()),
+        ..ResponseReferenceData::default()
+    };
+
+    let expiration = ExpirationDate::from_reference_data(&reference_data).unwrap();
+
+    assert_eq!(expiration, ExpirationDate { year: 2026, month: 12, day: 18 });
+    assert_eq!(ExpirationDate::parse("2026-12-18"), None);
+    assert_eq!(ExpirationDate::parse("20261318"), None);
+}
+
+#[test]
+fn days_to_expiry_counts_whole_utc_days() {
+    let expiration = ExpirationDate::parse("20261218").unwrap();
+
+    assert_eq!(ExpirationDate::parse("19700101").unwrap().days_since_epoch(), 0);
+    assert_eq!(ExpirationDate::parse("20000301").unwrap().days_since_epoch(), 11017);
+
+    // 2026-10-16 23:00 UTC
+    let now = UNIX_EPOCH + Duration::from_secs(20_742 * 86_400 + 23 * 3_600);
+
+    assert_eq!(expiration.days_from(now), 63);
+    assert_eq!(ExpirationDate::parse("20261016").unwrap().days_from(now), 0);
+    assert_eq!(ExpirationDate::parse("20261015").unwrap().days_from(now), -1);
+}
@@ -0,0 +1,96 @@
+//! Checks responses reach the caller of their request and updates the update channel, without
+//! a network.
+
+mod common;
+
+use tokio::sync::{broadcast, oneshot};
+
+use rithmic_client::{
+    api::{
+        receiver_api::RithmicReceiverApi,
+        templates::{LAST_TRADE, RESPONSE_MARKET_DATA_UPDATE},
+    },
+    request_handler::{RithmicRequest, RithmicRequestHandler},
+    rti::{LastTrade, ResponseMarketDataUpdate, messages::RithmicMessage},
+    ws::drain_updates,
+};
+
+use common::frame;
+
+#[test]
+fn subscription_response_goes_to_the_requester_and_trades_to_the_updates() {
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    let mut request_handler = RithmicRequestHandler::new();
+    let (updates_tx, mut updates_rx) = broadcast::channel(16);
+    let (tx, mut rx) = oneshot::channel();
+
+    request_handler.register_request(RithmicRequest {
+        request_id: "1".to_string(),
+        responder: tx,
+    });
+
+    let subscribed = frame(ResponseMarketDataUpdate {
+        template_id: RESPONSE_MARKET_DATA_UPDATE,
+        user_msg: vec!["1".to_string()],
+        rp_code: vec!["0".to_string()],
+    });
+
+    let trade = frame(LastTrade {
+        template_id: LAST_TRADE,
+        symbol: Some("ESZ6".to_string()),
+        exchange: Some("CME".to_string()),
+        trade_price: Some(5000.25),
+        ..LastTrade::default()
+    });
+
+    for data in [subscribed, trade.clone(), trade] {
+        let response = receiver_api.buf_to_message(data).unwrap();
+
+        request_handler.dispatch(response, &updates_tx);
+    }
+
+    let responses = rx.try_recv().unwrap().unwrap();
+    assert_eq!(responses.len(), 1);
+    assert!(matches!(responses[0].message, RithmicMessage::ResponseMarketDataUpdate(_)));
+
+    for _ in 0..2 {
+        let update = updates_rx.try_recv().unwrap();
+        assert!(matches!(update.message, RithmicMessage::LastTrade(_)));
+    }
+
+    assert!(updates_rx.try_recv().is_err());
+}
+
+#[test]
+fn replayed_updates_fail_when_some_were_dropped() {
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    let (updates_tx, mut updates_rx) = broadcast::channel(2);
+
+    let trade = frame(LastTrade {
+        template_id: LAST_TRADE,
+        symbol: Some("ESZ6".to_string()),
+        ..LastTrade::default()
+    });
+
+    let send_trades = |count| {
+        for _ in 0..count {
+            let _ = updates_tx.send(receiver_api.buf_to_message(trade.clone()).unwrap());
+        }
+    };
+
+    send_trades(2);
+    assert_eq!(drain_updates(&mut updates_rx).unwrap().len(), 2);
+
+    send_trades(3);
+    assert!(drain_updates(&mut updates_rx).is_err());
+}
@@ -0,0 +1,121 @@
+//! Checks multi-response streams end when the server rejects them, without a network.
+
+mod common;
+
+use bytes::Bytes;
+use tokio::sync::oneshot;
+
+use rithmic_client::{
+    api::{
+        receiver_api::{RithmicReceiverApi, RithmicResponse},
+        templates::{REJECT, RESPONSE_SEARCH_SYMBOLS},
+    },
+    request_handler::{RithmicRequest, RithmicRequestHandler},
+    rti::{Reject, ResponseSearchSymbols},
+};
+
+use common::frame;
+
+fn search_part(rq_handler_rp_code: &[&str]) -> Bytes {
+    frame(ResponseSearchSymbols {
+        template_id: RESPONSE_SEARCH_SYMBOLS,
+        user_msg: vec!["1".to_string()],
+        rq_handler_rp_code: rq_handler_rp_code.iter().map(|code| code.to_string()).collect(),
+        symbol: Some("ESZ6".to_string()),
+        ..ResponseSearchSymbols::default()
+    })
+}
+
+fn decode(receiver_api: &RithmicReceiverApi, data: Bytes) -> RithmicResponse {
+    receiver_api.buf_to_message(data).unwrap()
+}
+
+fn setup() -> (
+    RithmicReceiverApi,
+    RithmicRequestHandler,
+    oneshot::Receiver<Result<Vec<RithmicResponse>, String>>,
+) {
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    let mut request_handler = RithmicRequestHandler::new();
+    let (tx, rx) = oneshot::channel();
+
+    request_handler.register_request(RithmicRequest {
+        request_id: "1".to_string(),
+        responder: tx,
+    });
+
+    (receiver_api, request_handler, rx)
+}
+
+#[test]
+fn stream_error_code_ends_the_stream_with_an_error() {
+    let (receiver_api, mut request_handler, mut rx) = setup();
+
+    request_handler.handle_response(decode(&receiver_api, search_part(&["0"])));
+    assert!(rx.try_recv().is_err(), "stream completed before its last part");
+
+    let failed = decode(&receiver_api, search_part(&["5", "permission denied"]));
+    assert!(!failed.has_more);
+    assert_eq!(failed.error.as_deref(), Some("permission denied"));
+
+    request_handler.handle_response(failed);
+
+    let error = rx.try_recv().unwrap().unwrap_err();
+    assert!(error.contains("permission denied"), "{}", error);
+}
+
+#[test]
+fn reject_ends_the_stream_with_an_error() {
+    let (receiver_api, mut request_handler, mut rx) = setup();
+
+    request_handler.handle_response(decode(&receiver_api, search_part(&["0"])));
+
+    let reject = frame(Reject {
+        template_id: REJECT,
+        user_msg: vec!["1".to_string()],
+        rp_code: vec!["3".to_string(), "bad request".to_string()],
+    });
+
+    request_handler.handle_response(decode(&receiver_api, reject));
+
+    let error = rx.try_recv().unwrap().unwrap_err();
+    assert!(error.contains("bad request"), "{}", error);
+}
+
+#[test]
+fn rejected_first_part_is_an_error() {
+    let (receiver_api, mut request_handler, mut rx) = setup();
+
+    let rejected = decode(&receiver_api, search_part(&["5", "permission denied"]));
+    request_handler.handle_response(rejected);
+
+    let error = rx.try_recv().unwrap().unwrap_err();
+    assert_eq!(error, "permission denied");
+}
+
+#[test]
+fn responses_keep_their_full_response_code() {
+    let (receiver_api, _request_handler, _rx) = setup();
+
+    let part = decode(&receiver_api, search_part(&["0"]));
+    assert!(part.response_code.is_success());
+
+    let reject = decode(
+        &receiver_api,
+        frame(Reject {
+            template_id: REJECT,
+            user_msg: vec!["1".to_string()],
+            rp_code: vec!["3".to_string(), "bad request".to_string(), "field 110100".to_string()],
+        }),
+    );
+
+    assert!(!reject.response_code.is_success());
+    assert_eq!(reject.response_code.code, "3");
+    assert_eq!(reject.response_code.message.as_deref(), Some("bad request"));
+    assert_eq!(reject.response_code.extra, vec!["field 110100".to_string()]);
+}
@@ -0,0 +1,116 @@
+//! Checks the template registry against the sender and receiver, without a network or the
+//! protocol reference PDF.
+
+mod common;
+
+use bytes::Bytes;
+
+use rithmic_client::{
+    api::{
+        receiver_api::RithmicReceiverApi, supported_request_templates,
+        supported_response_templates,
+    },
+    rti::{MessageType, Reject},
+};
+
+use common::frame;
+
+/// Minimal frame for `template_id`: template id and a user_msg, the fields every response
+/// carries. They have the same tags in every message, a `Reject` encodes them for any template.
+fn minimal_frame(template_id: i32) -> Bytes {
+    frame(Reject {
+        template_id,
+        user_msg: vec!["1".to_string()],
+        ..Reject::default()
+    })
+}
+
+#[test]
+fn every_request_has_its_response_handled() {
+    let responses = supported_response_templates();
+
+    // R | Protocol responses use the template id following their request's
+    let missing: Vec<u16> = supported_request_templates()
+        .iter()
+        .filter(|request| !responses.contains(&(*request + 1)))
+        .copied()
+        .collect();
+
+    assert!(missing.is_empty(), "requests without a handled response: {:?}", missing);
+}
+
+#[test]
+fn template_lists_are_sorted() {
+    for templates in [supported_request_templates(), supported_response_templates()] {
+        assert!(templates.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", templates);
+    }
+}
+
+#[test]
+fn every_supported_response_is_decoded() {
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    // Some templates are decoded into an error, e.g. a forced logout, only an unhandled template
+    // means the decoder is missing
+    let undecoded: Vec<u16> = supported_response_templates()
+        .iter()
+        .filter(|template_id| {
+            receiver_api
+                .buf_to_message(minimal_frame(**template_id as i32))
+                .is_err_and(|e| e.starts_with("unhandled template id"))
+        })
+        .copied()
+        .collect();
+
+    assert!(undecoded.is_empty(), "supported but not decoded: {:?}", undecoded);
+}
+
+#[test]
+fn captured_frames_decode_from_hex_and_base64() {
+    use base64::{Engine, prelude::BASE64_STANDARD};
+    use rithmic_client::{
+        api::{decode_base64, decode_hex, templates::REJECT},
+        rti::messages::RithmicMessage,
+    };
+
+    let data = minimal_frame(REJECT);
+    let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+
+    for response in [
+        decode_hex(&hex).unwrap(),
+        decode_hex(&hex[8..]).unwrap(),
+        decode_base64(&BASE64_STANDARD.encode(&data)).unwrap(),
+    ] {
+        assert!(matches!(response.message, RithmicMessage::Reject(_)));
+        assert_eq!(response.request_id, "1");
+    }
+
+    assert!(decode_hex("0g").is_err());
+    assert!(decode_hex("123").is_err());
+}
+
+#[test]
+fn malformed_frames_are_errors() {
+    use rithmic_client::api::templates::RESPONSE_GET_INSTRUMENT_BY_UNDERLYING;
+
+    let receiver_api = RithmicReceiverApi {
+        source: "test".to_string(),
+        frame_capture: None,
+        template_version: None,
+    };
+
+    assert!(receiver_api.buf_to_message(Bytes::from_static(&[0, 0])).is_err());
+    assert!(receiver_api.buf_to_message(Bytes::from_static(&[0, 0, 0, 2, 0xff, 0xff])).is_err());
+
+    // The request is sent without a user_msg, so the response carries none
+    let data = frame(MessageType {
+        template_id: RESPONSE_GET_INSTRUMENT_BY_UNDERLYING,
+    });
+
+    let response = receiver_api.buf_to_message(data).unwrap();
+    assert_eq!(response.request_id, "");
+}